@@ -0,0 +1,67 @@
+// Connect4EngineRust, a strong solver for the connect-4 board game.
+// Copyright (C) 2023 Derick Tseng
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Generates `generated_tables.rs` in cargo's `OUT_DIR` (pulled into `src/board.rs` via
+//! `include!`), a file of `const` lookup tables mirroring the bitboard
+//! layout constants in `src/board.rs` (`WIDTH`, `HEIGHT`, `COUNTS_PER_COL`, `DIRECTION`). These
+//! back `Board::col_to_pos`/`col_is_occupied`/`get_height` and `winning_moves`/`is_win`, which
+//! otherwise re-derive the same per-column masks and per-direction shift multiples on every call
+//! in the hot search loop. Kept in sync with `board.rs` by hand, since `build.rs` can't `use` the
+//! crate it's building for.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const HEIGHT: u64 = 6;
+const WIDTH: u64 = 7;
+const COUNTS_PER_COL: u64 = 7;
+const COLUMN_MASK: u64 = (1 << HEIGHT) - 1;
+const DIRECTION: [u64; 4] = [1, COUNTS_PER_COL - 1, COUNTS_PER_COL, COUNTS_PER_COL + 1];
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set by cargo");
+    let dest_path = Path::new(&out_dir).join("generated_tables.rs");
+
+    let column_masks: Vec<u64> = (0..WIDTH).map(|c| COLUMN_MASK << (c * COUNTS_PER_COL)).collect();
+
+    // for each of the 4 `DIRECTION` shift amounts, the shift itself and its 2x/3x multiples, used
+    // by `winning_moves`/`is_win`'s shift chains instead of computing `2 * dir`/`3 * dir` inline.
+    let direction_shifts: Vec<[u64; 3]> = DIRECTION.iter().map(|&dir| [dir, 2 * dir, 3 * dir]).collect();
+
+    let mut generated = String::new();
+    generated.push_str("// generated by build.rs - do not edit by hand.\n\n");
+
+    generated.push_str(&format!(
+        "pub const COLUMN_MASKS: [Position; {}] = [{}];\n\n",
+        WIDTH,
+        column_masks.iter().map(|m| format!("0x{:x}", m)).collect::<Vec<_>>().join(", "),
+    ));
+
+    generated.push_str(&format!(
+        "pub const DIRECTION_SHIFTS: [[u8; 3]; {}] = [{}];\n",
+        DIRECTION.len(),
+        direction_shifts
+            .iter()
+            .map(|shifts| format!("[{}, {}, {}]", shifts[0], shifts[1], shifts[2]))
+            .collect::<Vec<_>>()
+            .join(", "),
+    ));
+
+    fs::write(&dest_path, generated).expect("failed to write generated_tables.rs");
+
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -1,5 +1,10 @@
 use std::fmt;
+use std::sync::OnceLock;
 use anyhow::{Result, bail};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::moves::EMPTY_MOVE;
 
 pub type Position = u64;
 
@@ -30,6 +35,54 @@ pub const COUNTS_PER_COL: u8 = 7;
 /// down, up-left, left, down-left directions of bitboard
 pub const DIRECTION: [u8; 4] = [1, COUNTS_PER_COL - 1, COUNTS_PER_COL, COUNTS_PER_COL + 1];
 
+// `COLUMN_MASKS` (per-column `COLUMN_MASK << (col * COUNTS_PER_COL)`) and `DIRECTION_SHIFTS`
+// (each `DIRECTION` entry alongside its 2x/3x multiples) are precomputed at build time by
+// `build.rs` rather than re-derived on every call, since `col_to_pos`/`col_is_occupied`/
+// `get_height`/`winning_moves`/`is_win` sit in the hot search loop.
+include!(concat!(env!("OUT_DIR"), "/generated_tables.rs"));
+
+/// number of bits spanned by the bitboard layout (including the unused skip bit at the top of
+/// each column), i.e. the range of bit positions `Board::hash`/`mirror_hash` need a random value
+/// for.
+const ZOBRIST_BITS: usize = (WIDTH * COUNTS_PER_COL) as usize;
+
+/// random values used to incrementally maintain `Board::hash`/`mirror_hash`: `keys[player][bit]`
+/// for every bit position in the bitboard layout, plus one value folded in on every move so the
+/// hash also depends on whose turn it is.
+struct ZobristTable {
+    keys: [[u64; ZOBRIST_BITS]; 2],
+    side_to_move: u64,
+}
+
+static ZOBRIST: OnceLock<ZobristTable> = OnceLock::new();
+
+/// lazily builds, then reuses, the Zobrist random table. Seeded deterministically (rather than
+/// from OS entropy) so that position hashes, and the transposition-table keys derived from them,
+/// are reproducible from run to run.
+fn zobrist_table() -> &'static ZobristTable {
+    ZOBRIST.get_or_init(|| {
+        let mut rng = StdRng::seed_from_u64(0xC4_C4_C4_C4_C4_C4_C4_C4);
+        let mut keys = [[0u64; ZOBRIST_BITS]; 2];
+        for player_keys in keys.iter_mut() {
+            for key in player_keys.iter_mut() {
+                *key = rng.gen();
+            }
+        }
+        let side_to_move = rng.gen();
+        ZobristTable { keys, side_to_move }
+    })
+}
+
+/// mirrors a bit position across the board's vertical center line, swapping column `c` with
+/// column `WIDTH - 1 - c` while leaving the row unchanged. Used to maintain `Board::mirror_hash`,
+/// the Zobrist hash of the horizontally-reflected position.
+fn mirror_bit(bit: usize) -> usize {
+    let col = bit / COUNTS_PER_COL as usize;
+    let row = bit % COUNTS_PER_COL as usize;
+    let mirror_col = (WIDTH - 1) as usize - col;
+    mirror_col * COUNTS_PER_COL as usize + row
+}
+
 /// Bitboard implementation of the Connect 4 Board.
 /// 
 /// The Board is represented as a 64 bit integer, with bits
@@ -56,6 +109,26 @@ pub const DIRECTION: [u8; 4] = [1, COUNTS_PER_COL - 1, COUNTS_PER_COL, COUNTS_PE
 pub struct Board {
     board: Position,
     total_board: Position,
+
+    /// incrementally-maintained Zobrist hash of this exact position, XOR-updated in `play`/
+    /// `revert` rather than recomputed from scratch. See `get_unique_position_key`.
+    hash: u64,
+
+    /// Zobrist hash of this position reflected left-right (column `c` <-> `WIDTH - 1 - c`),
+    /// maintained alongside `hash` the same way. Connect-4 openings are symmetric under this
+    /// reflection, so folding both hashes together lets mirror-equivalent positions share one
+    /// transposition-table entry.
+    mirror_hash: u64,
+
+    /// incrementally-maintained ply counter, kept in sync with `total_board.count_ones()` by
+    /// `play`/`revert` so `moves_played` doesn't have to re-count bits every call. See
+    /// `moves_played`.
+    moves: u32,
+
+    /// the current player's immediate winning squares (`player_win_moves` restricted to this
+    /// position), refreshed in `play`/`revert` alongside `moves` so search doesn't need to
+    /// recompute it on every node. See `current_threats`.
+    current_threats: Position,
 }
 
 impl fmt::Display for Board {
@@ -92,6 +165,10 @@ impl Board {
         Self {
             board: 0,
             total_board: 0,
+            hash: 0,
+            mirror_hash: 0,
+            moves: 0,
+            current_threats: 0,
         }
     }
 
@@ -152,8 +229,7 @@ impl Board {
 
     /// used only for testing purposes. Should not use.
     pub fn get_height(&self, col: u8) -> u8 {
-        let col_mask = COLUMN_MASK << (col * COUNTS_PER_COL);
-        let board_column = col_mask & self.total_board;
+        let board_column = COLUMN_MASKS[col as usize] & self.total_board;
         board_column.count_ones() as u8
     }
 
@@ -165,8 +241,7 @@ impl Board {
 
     /// returns true if the entire column is occupied.
     fn col_is_occupied(board: Position, col: u8) -> bool {
-        let col_mask = COLUMN_MASK << (col * COUNTS_PER_COL);
-        let top_bit = TOP_ROW_MASK & col_mask;
+        let top_bit = TOP_ROW_MASK & COLUMN_MASKS[col as usize];
         (board & top_bit) != 0
     }
 
@@ -185,8 +260,7 @@ impl Board {
 
     /// converts the column [0, 6] to the bit position to play.
     pub fn col_to_pos(possible: Position, col: u8) -> Position {
-        let col_mask = COLUMN_MASK << (col * COUNTS_PER_COL);
-        possible & col_mask
+        possible & COLUMN_MASKS[col as usize]
     }
 
     /// converts the bit position to play into a column.
@@ -242,15 +316,16 @@ impl Board {
         let mut win_moves = (p << 1) & (p << 2) & (p << 3);
 
         // All of the other directions aside from vertical.
-        for &dir in &DIRECTION[1..] {
-            let mut pp = (p << dir) & (p << (2 * dir));
+        for &[dir, dir2, dir3] in &DIRECTION_SHIFTS[1..] {
+            let (dir, dir2, dir3) = (dir as Position, dir2 as Position, dir3 as Position);
+            let mut pp = (p << dir) & (p << dir2);
 
-            win_moves |= pp & (p << (3 * dir)); // 3 in a row (e.g. xxx_)
+            win_moves |= pp & (p << dir3); // 3 in a row (e.g. xxx_)
             win_moves |= pp & (p >> dir); // split (e.g. x_xx)
 
             // for use in the other direction.
-            pp >>= 3 * dir;
-            win_moves |= pp & (p >> (3 * dir)); // 3 in a row
+            pp >>= dir3;
+            win_moves |= pp & (p >> dir3); // 3 in a row
             win_moves |= pp & (p << dir); // split
         }
 
@@ -265,20 +340,77 @@ impl Board {
     /// performs the add operation assuming that the selected position can be played.
     /// Undefined behavior if position is not valid.
     pub fn play(&mut self, pos: Position) {
+        self.toggle_zobrist(pos, self.moves_played());
+
         // updates the board to the current player.
         self.board ^= self.total_board;
 
         // updates the board
         self.total_board |= pos;
         self.board |= pos;
+
+        self.moves += 1;
+        self.update_current_threats();
+    }
+
+    /// validated, non-mutating counterpart to `play`: returns a fresh `Board` with `pos` applied,
+    /// or `None` if `pos` isn't a legal move (not exactly one bit, or not among
+    /// `possible_moves()`) - `self` is left untouched either way. Useful for iterator-driven or
+    /// multithreaded expansion, where paired `play`/`revert` bookkeeping on one shared `Board` is
+    /// awkward.
+    pub fn play_checked(&self, pos: Position) -> Option<Board> {
+        if pos == 0 || !Board::at_most_one_bit_set(pos) || pos & self.possible_moves() != pos {
+            return None;
+        }
+
+        let mut next = *self;
+        next.play(pos);
+        Some(next)
     }
 
     pub fn revert(&mut self, pos: Position) {
+        // `pos` was played when `moves_played() - 1` stones were already down, since the `moves`
+        // counter hasn't been decremented yet.
+        self.toggle_zobrist(pos, self.moves_played() - 1);
+
         // reverts the added position.
         self.total_board ^= pos;
         self.board ^= pos;
 
         self.board ^= self.total_board;
+
+        self.moves -= 1;
+        self.update_current_threats();
+    }
+
+    /// refreshes `current_threats` from the now-current `board`/`total_board`. Unlike the Zobrist
+    /// hashes, win-threat detection doesn't decompose into a cheap per-bit XOR delta, so this
+    /// recomputes via the same O(1) `winning_moves` bit-twiddling `player_win_moves` uses rather
+    /// than maintaining a true incremental patch - `play`/`revert` still avoid a full rescan of
+    /// the board, which is what `current_threats` exists to spare callers from doing themselves.
+    fn update_current_threats(&mut self) {
+        let possible = self.possible_moves();
+        self.current_threats = Board::winning_moves(self.get_curr_player_pos(), possible);
+    }
+
+    /// the current player's cached immediate winning squares, incrementally refreshed by `play`/
+    /// `revert` - equivalent to `player_win_moves(self.possible_moves())` but without recomputing
+    /// `possible_moves` or re-deriving the threat mask.
+    pub fn current_threats(&self) -> Position {
+        self.current_threats
+    }
+
+    /// XORs the Zobrist contribution of the single stone at `pos`, played when `moves_played`
+    /// stones were already on the board, into both `hash` and `mirror_hash`. XOR is its own
+    /// inverse, so calling this with the same arguments a second time undoes it again - `play` and
+    /// `revert` share this helper for exactly that reason.
+    fn toggle_zobrist(&mut self, pos: Position, moves_played: u32) {
+        let player = (moves_played % 2) as usize;
+        let bit = pos.trailing_zeros() as usize;
+
+        let table = zobrist_table();
+        self.hash ^= table.keys[player][bit] ^ table.side_to_move;
+        self.mirror_hash ^= table.keys[player][mirror_bit(bit)] ^ table.side_to_move;
     }
 
     /// returns true if the bitboard is a winner.
@@ -286,10 +418,11 @@ impl Board {
     /// We do not need an option for checking if this current player has lost
     /// because you cannot lose the game on the turn you played your move.
     pub fn is_win(bitboard: Position) -> bool {
-        for dir in DIRECTION {
+        for [dir, dir2, _] in DIRECTION_SHIFTS {
+            let (dir, dir2) = (dir as Position, dir2 as Position);
             // checks two at a time for better efficiency.
             let bb = bitboard & (bitboard >> dir);
-            if (bb & (bb >> (2 * dir))) != 0 {
+            if (bb & (bb >> dir2)) != 0 {
                 return true;
             }
         }
@@ -302,6 +435,11 @@ impl Board {
         self.total_board ^ self.board
     }
 
+    /// returns the position from the opponent's perspective (the player who just moved).
+    pub fn get_opp_player_pos(&self) -> Position {
+        self.board
+    }
+
     /// Returns a new position with `mv` played on `pos`.
     /// Assumes that mv can be played, and pos is valid. Undefined behavior if it is not.
     pub fn test_pos(pos: Position, mv: Position) -> Position {
@@ -324,55 +462,39 @@ impl Board {
         self.is_first_player_win() || self.is_second_player_win() || self.is_filled()
     }
 
-    /// obtains the number of moves made.
-    /// Should not continue to call in heavy calculations. Instead, it is recommended to add and
-    /// subtract from a local variable as necessary whenever a move gets played.
+    /// obtains the number of moves made, backed by the `moves` counter `play`/`revert` maintain
+    /// incrementally - safe to call as often as needed in heavy calculations.
     pub fn moves_played(&self) -> u32 {
-        self.total_board.count_ones()
+        self.moves
     }
 
-    /// obtains the unique position key. This is calculated by
-    /// obtaining the top bound of the total board for each column
-    /// then shifting it upwards by 1, then xor with the player board.
-    ///
-    /// e.g. if
-    /// player board:
-    /// 0 0 0 0 0 0 0
-    /// 0 1 0 0 1 0 0
-    /// 1 0 0 0 0 0 0
-    /// 0 1 1 0 1 0 0
-    /// 0 1 1 0 1 0 0
-    /// 1 0 0 0 0 0 0
-    ///
-    /// total board:
-    /// 0 0 0 0 1 0 0
-    /// 1 1 0 0 1 0 0
-    /// 1 1 0 0 1 0 0
-    /// 1 1 1 0 1 0 0
-    /// 1 1 1 0 1 0 0
-    /// 1 1 1 1 1 0 0
-    ///
-    /// top bound of total board:
-    /// 0 0 0 0 1 0 0
-    /// 1 1 0 0 0 0 0
-    /// 0 0 0 0 0 0 0
-    /// 0 0 1 0 0 0 0
-    /// 0 0 0 0 0 0 0
-    /// 0 0 0 1 0 0 0
-    ///
-    /// We shift the top bound up by 1 to get the bounding limits
-    /// of the playable board. This works because a slot of `0` below the
-    /// bounding limits implies that the slot is occupied by the first player,
-    /// while zeroes above mean empty.
+    /// obtains the unique position key used to index the transposition table: the smaller of
+    /// `hash` and `mirror_hash`, so that a position and its horizontal mirror (column `c` <->
+    /// `WIDTH - 1 - c`) resolve to the same key. Both hashes are maintained incrementally by
+    /// `play`/`revert`, so this is an O(1) lookup rather than a recomputation from scratch.
     pub fn get_unique_position_key(&self) -> u64 {
-        // OLD WAY
-        // let bounding_limits = self.total_board + BOTTOM_ROW_MASK;
-        // bounding_limits ^ self.board
-        
-        // the old way had me adding BOTTOM_ROW_MASK in the calculation for
-        // unique position key. This is just a wasted instruction and can be
-        // removed.
-        self.total_board + self.board
+        u64::min(self.hash, self.mirror_hash)
+    }
+
+    /// true if `get_unique_position_key` resolved to this board's *mirrored* hash rather than
+    /// its own, i.e. any TT/book entry stored under that key was written in the mirrored
+    /// orientation (column `c` <-> `WIDTH - 1 - c`) relative to this board. Used by
+    /// `canonicalize_col` to keep a stored move's column meaningful despite the key merge.
+    fn is_mirrored_canonical(&self) -> bool {
+        self.mirror_hash < self.hash
+    }
+
+    /// maps `col` between this board's own orientation and the orientation `get_unique_position_key`
+    /// canonicalized to. Self-inverse: applying it once (relative to this board) when storing a
+    /// move under the canonical key, and again (relative to whichever board later reads the key
+    /// back, possibly its mirror image) when retrieving it, yields the correct column for each
+    /// board's own orientation. `EMPTY_MOVE` passes through unchanged, since it isn't a column.
+    pub fn canonicalize_col(&self, col: u8) -> u8 {
+        if col == EMPTY_MOVE || !self.is_mirrored_canonical() {
+            col
+        } else {
+            WIDTH - 1 - col
+        }
     }
 
     pub fn is_first_player_win(&self) -> bool {
@@ -31,6 +31,35 @@ pub struct Cli {
     /// enables use NNUE as evaluator.
     #[arg(long, default_value_t=false)]
     pub nnue: bool,
+
+    /// number of worker threads to search with (Lazy SMP). Defaults to a single-threaded search.
+    #[arg(long, default_value_t=1)]
+    pub threads: usize,
+
+    /// caps thinking time in milliseconds. When set, the engine returns the best move found so
+    /// far instead of solving to completion. Leave unset for an unbounded, exact solve.
+    #[arg(long)]
+    pub movetime: Option<u64>,
+
+    /// pre-loads the transposition table from a file previously written by `--tt-save`, instead
+    /// of starting the search from an empty table.
+    #[arg(long)]
+    pub tt_load: Option<String>,
+
+    /// saves the transposition table to a file after solving, so a long solve can be checkpointed
+    /// and resumed, or shipped as a warm cache for later runs.
+    #[arg(long)]
+    pub tt_save: Option<String>,
+
+    /// precomputed opening book file (see `DB book`) to probe before searching; a hit returns
+    /// instantly without running the solver.
+    #[arg(long)]
+    pub book: Option<String>,
+
+    /// precomputed endgame tablebase file (see `DB endgame-book`) to probe at every search node;
+    /// a hit returns its exact evaluation instantly without searching the position further.
+    #[arg(long)]
+    pub endgame_table: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -51,6 +80,10 @@ pub enum Commands {
     /// Evaluate a given position.
     Eval { position: String },
 
+    /// Speak the text command protocol over stdin/stdout (see the `protocol` module), for
+    /// driving the engine from an external GUI or test harness.
+    Protocol,
+
     /// Create a database of positions.
     DB {
         #[command(subcommand)]
@@ -88,5 +121,25 @@ pub enum DBCommands {
     Mirror {
         /// the database file to mirror.
         src_file: String
-    }
+    },
+
+    /// solves all positions up to a given ply and writes them to a memory-mappable
+    /// `PersistentTable` file, for use as a warm-start cache or opening book.
+    Book {
+        /// maximum ply depth (moves played) to solve and store.
+        #[arg(long, default_value_t=8)]
+        max_ply: u8,
+    },
+
+    /// samples random near-terminal positions, solves each exactly, and writes them to a
+    /// memory-mappable `PersistentTable` file, for use as an endgame tablebase.
+    EndgameBook {
+        /// maximum number of empty cells a sampled position may have.
+        #[arg(long, default_value_t=8)]
+        max_empty: u8,
+
+        /// number of distinct positions to sample and solve.
+        #[arg(long, default_value_t=10000)]
+        num_positions: usize,
+    },
 }
@@ -36,6 +36,11 @@ pub struct ThreatCountEvaluator {}
 /// Evaluator that calculates the score of a move based on neural network.
 pub struct NnueEvaluator {
     nnue: Nnue,
+
+    /// (current player's, opponent's) bitboards of the position the accumulator was last
+    /// `refresh`ed for, so `eval` only pays for a full rebuild when it is handed a position outside
+    /// the line the accumulator is tracking.
+    accumulator_key: Option<(Position, Position)>,
 }
 
 
@@ -59,25 +64,27 @@ impl Evaluator for NnueEvaluator {
     fn new() -> Self {
         let modelpath = Path::new("nnue/export_model.pth");
         let nnue = Nnue::new(modelpath, tch::Device::Cpu).unwrap();
-        Self { nnue }
+        Self { nnue, accumulator_key: None }
     }
 
     fn eval(&mut self, board: &Board, mv: Position) -> i8 {
-        // we need to "pretend" that the player made the move already.
-        let opp_player = board.get_curr_player_pos() | mv;
-        let curr_player = board.get_opp_player_pos();
-
-        let moves = board.moves_played() + 1;
-        let p2mv = (moves % 2) as u8;
-
-        let (p0, p1) = if p2mv == 0 {
-            (curr_player, opp_player)
-        } else {
-            (opp_player, curr_player)
-        };
+        // the accumulator only updates incrementally along a single line of play; if `board`
+        // isn't the position it was last left representing, rebuild it from scratch first.
+        let key = (board.get_curr_player_pos(), board.get_opp_player_pos());
+        if self.accumulator_key != Some(key) {
+            self.nnue.refresh(board);
+            self.accumulator_key = Some(key);
+        }
+
+        // "pretend" that the player made the move already, mirroring Board::play/revert so the
+        // accumulator is left exactly as it was once we're done.
+        let col = Board::pos_to_col(mv);
+        self.nnue.push(mv, col);
+        let value = self.nnue.evaluate() as i8;
+        self.nnue.pop();
 
         // we return the negative score since if this position after the player played `mv` is bad,
         // that means the move must have been good for the player who made the move.
-        -self.nnue.evaluate(p0, p1, p2mv, moves)
+        -value
     }
 }
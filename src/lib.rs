@@ -2,6 +2,9 @@ pub mod board;
 pub mod strategy;
 pub mod moves;
 pub mod transpositiontable;
+pub mod persistenttable;
 pub mod scoredmoves;
 pub mod database;
 pub mod cli;
+pub mod worker;
+pub mod protocol;
@@ -16,14 +16,19 @@
 
 use clap::Parser;
 use connect4engine::board::SIZE;
-use connect4engine::cli::{Cli, Commands};
+use connect4engine::cli::{Cli, Commands, DBCommands};
 use connect4engine::database::Database;
 use connect4engine::moves::EMPTY_MOVE;
+use connect4engine::persistenttable::PersistentTable;
+use connect4engine::transpositiontable::TranspositionTable;
+use connect4engine::worker::AnalysisWorker;
 use connect4engine::{strategy::Explorer, board::Board};
 use std::fs;
-use std::time::Instant;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::io::{self, BufReader, BufRead, Write};
-use anyhow::Result;
+use anyhow::{bail, Result};
 
 /// main function
 fn main() -> Result<()> {
@@ -31,59 +36,162 @@ fn main() -> Result<()> {
 
     // if no command inputted, run the stdin.
     if cli.command.is_none() {
-        eval_from_stdin()?;
+        eval_from_stdin(&cli)?;
         return Ok(())
     }
 
     // command was inputted. Need to parse.
-    match &cli.command.unwrap() {
+    match cli.command.as_ref().unwrap() {
         Commands::Test { file } => test_files(file)?,
-        Commands::Eval { position } => eval_position(position)?,
-        Commands::Play { position } => play_position(position.as_deref())?,
-        Commands::DB(db) => create_database(&db.file, db.max, db.min, db.num, db.stdin)?,
+        Commands::Eval { position } => eval_position(position, &cli)?,
+        Commands::Play { position } => play_position(position.as_deref(), &cli)?,
+        Commands::Protocol => run_protocol()?,
+        Commands::DB { db_cmd, file } => run_db_command(db_cmd, file)?,
     };
 
     Ok(())
 }
 
-/// creates a sqlite3 database of positions at the specified location.
-fn create_database(filename: &str, max: u8, min: u8, num: usize, stdin: bool) -> Result<()> {
-    let mut db = Database::new(filename);
+/// runs the text command protocol (see `connect4engine::protocol`) over stdin/stdout.
+fn run_protocol() -> Result<()> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    connect4engine::protocol::run(stdin.lock(), stdout.lock())
+}
 
-    if stdin { // positions from stdin.
-        let mut positions = Vec::new();
+/// true if any of `--tt-load`/`--tt-save`/`--book`/`--endgame-table` are set, meaning the caller
+/// needs a dedicated, persistable `Explorer` rather than the stateless `Explorer::solve_lazy_smp`
+/// entry point.
+fn needs_dedicated_explorer(cli: &Cli) -> bool {
+    cli.tt_load.is_some() || cli.tt_save.is_some() || cli.book.is_some()
+        || cli.endgame_table.is_some()
+}
 
-        loop {
-            let mut buf = String::with_capacity(SIZE as usize + 1);
-            let r = io::stdin().read_line(&mut buf)?;
-            if r == 0 { break; }
-            let strpos = String::from(buf.trim());
-            positions.push(strpos);
-        }
+/// builds the `Explorer` used whenever persistence flags are set: pre-loads `--tt-load`'s table
+/// if given, otherwise starts from an empty one, then attaches `--book` and `--endgame-table` if
+/// given.
+fn build_explorer(cli: &Cli) -> Result<Explorer> {
+    let mut explorer = match &cli.tt_load {
+        Some(path) => Explorer::with_table(Arc::new(TranspositionTable::load(Path::new(path))?)),
+        None => Explorer::new(),
+    };
+
+    if let Some(path) = &cli.book {
+        explorer = explorer.with_book(Arc::new(PersistentTable::load(Path::new(path))?));
+    }
+
+    if let Some(path) = &cli.endgame_table {
+        explorer = explorer.with_endgame_table(Arc::new(PersistentTable::load(Path::new(path))?));
+    }
+
+    Ok(explorer)
+}
 
-        // put positions in the database.
-        db.write_entries_from_list(positions.as_slice())?;
+/// saves `explorer`'s transposition table to `--tt-save`'s path, if set.
+fn maybe_save_table(explorer: &Explorer, cli: &Cli) -> Result<()> {
+    if let Some(path) = &cli.tt_save {
+        explorer.transposition_table().save(Path::new(path))?;
     }
-    else { // generate random positions
-        db.write_entries(num, max, min)?;
+    Ok(())
+}
+
+/// solves `board` the way `cli`'s flags request: a dedicated, persistable `Explorer` if any of
+/// `--tt-load`/`--tt-save`/`--book`/`--endgame-table` are set, otherwise the ordinary (optionally time-bounded or
+/// Lazy SMP) path. Returns the move, its eval, and the principal variation from the root.
+fn solve_with_cli(board: &Board, cli: &Cli) -> Result<(u8, i8, Vec<u8>)> {
+    if needs_dedicated_explorer(cli) {
+        let mut explorer = build_explorer(cli)?;
+        let deadline = cli.movetime.map(Duration::from_millis);
+        let (mv, eval) = explorer.solve_timed(board, deadline);
+        let pv = explorer.principal_variation(board);
+        maybe_save_table(&explorer, cli)?;
+        return Ok((mv, eval, pv));
+    }
+
+    Ok(match cli.movetime {
+        Some(ms) => solve_timed(board, ms),
+        None => Explorer::solve_lazy_smp(board, cli.threads),
+    })
+}
+
+/// dispatches one of the `DB` subcommands onto the database / persistent-table file `file`.
+fn run_db_command(db_cmd: &DBCommands, file: &str) -> Result<()> {
+    match db_cmd {
+        DBCommands::Random { num, min, max } => create_database(file, *max, *min, *num)?,
+        DBCommands::Stdin => create_database_from_stdin(file)?,
+        DBCommands::Mirror { src_file: _ } => bail!("DB mirror is not yet implemented"),
+        DBCommands::Book { max_ply } => PersistentTable::build(Path::new(file), *max_ply)?,
+        DBCommands::EndgameBook { max_empty, num_positions } =>
+            PersistentTable::build_endgame(Path::new(file), *max_empty, *num_positions)?,
+    }
+    Ok(())
+}
+
+/// creates a sqlite3 database of `num` random positions at the specified location.
+fn create_database(filename: &str, max: u8, min: u8, num: usize) -> Result<()> {
+    let mut db = Database::new(filename);
+    db.write_entries_random(num, max, min)?;
+    Ok(())
+}
+
+/// creates a sqlite3 database from positions read from stdin.
+fn create_database_from_stdin(filename: &str) -> Result<()> {
+    let mut db = Database::new(filename);
+    let mut positions = Vec::new();
+
+    loop {
+        let mut buf = String::with_capacity(SIZE as usize + 1);
+        let r = io::stdin().read_line(&mut buf)?;
+        if r == 0 { break; }
+        let strpos = String::from(buf.trim());
+        positions.push(strpos);
     }
+
+    // put positions in the database.
+    db.write_entries_from_list(positions.as_slice())?;
     Ok(())
 }
 
-/// prints the evaluation and optimal move for a given position.
-fn eval_position(pos: &str) -> Result<()> {
+/// prints the evaluation and optimal move for a given position, per `cli`'s `--movetime`/
+/// `--tt-load`/`--tt-save`/`--book` flags.
+fn eval_position(pos: &str, cli: &Cli) -> Result<()> {
     let board = Board::new_position(pos)?;
-    let mut explorer = Explorer::new();
-    let (mv, eval) = explorer.solve(&board);
+    let (mv, eval, pv) = solve_with_cli(&board, cli)?;
 
-    print_eval(mv, eval);
+    print_eval(mv, eval, &pv);
     Ok(())
 }
 
+/// solves `board` on a background `AnalysisWorker`, blocking for at most `movetime_ms` before
+/// reporting whatever move the worker has settled on. Along the way, prints an "info" line for
+/// every iterative-deepening iteration the worker reports, so the caller can watch the search
+/// converge instead of only seeing the final answer.
+fn solve_timed(board: &Board, movetime_ms: u64) -> (u8, i8, Vec<u8>) {
+    let worker = AnalysisWorker::spawn();
+    worker.set_time_limit(Duration::from_millis(movetime_ms));
+    let (reply, progress) = worker.start_with_progress(*board);
+
+    let mut pv = Vec::new();
+    for info in &progress {
+        println!("info iteration {} eval {} nodes {} pv {}", info.iteration, info.eval,
+            info.nodes, format_pv(&info.pv));
+        pv = info.pv;
+    }
 
-/// reads positions from stdin and outputs the evaluation and best move into stdout.
-fn eval_from_stdin() -> Result<()> {
+    let (mv, eval) = reply.recv().expect("analysis worker dropped its reply channel before answering");
+    (mv, eval, pv)
+}
+
+/// reads positions from stdin and outputs the evaluation and best move into stdout, per `cli`'s
+/// `--movetime`/`--tt-load`/`--tt-save`/`--book` flags. If `--movetime` is set, each position is
+/// capped to that many milliseconds and the best move found so far is emitted once the clock runs
+/// out.
+fn eval_from_stdin(cli: &Cli) -> Result<()> {
     let mut buf = String::new();
+
+    // reused across positions unless a dedicated, persistable `Explorer` is needed (see
+    // `solve_with_cli`), in which case one is built fresh per position so `--tt-save` always
+    // captures that position's search.
     let mut explorer = Explorer::new();
 
     loop {
@@ -103,8 +211,19 @@ fn eval_from_stdin() -> Result<()> {
 
         // new position has been inputted. We can solve.
         let board = b.unwrap();
-        let (mv, eval) = explorer.solve(&board);
-        print_eval(mv, eval);
+        let (mv, eval, pv) = if needs_dedicated_explorer(cli) {
+            solve_with_cli(&board, cli)?
+        } else {
+            match cli.movetime {
+                Some(ms) => solve_timed(&board, ms),
+                None => {
+                    let (mv, eval) = explorer.solve(&board);
+                    let pv = explorer.principal_variation(&board);
+                    (mv, eval, pv)
+                }
+            }
+        };
+        print_eval(mv, eval, &pv);
 
         // flush output immediately.
         io::stdout().flush()?;
@@ -113,8 +232,9 @@ fn eval_from_stdin() -> Result<()> {
     Ok(())
 }
 
-/// Prints the evaluation and move, taking care of game over scenarios.
-fn print_eval(mv: u8, eval: i8) {
+/// Prints the evaluation and move, taking care of game over scenarios, followed by the principal
+/// variation from this position (if any), as columns in [1-7].
+fn print_eval(mv: u8, eval: i8, pv: &[u8]) {
     if mv == EMPTY_MOVE {
         // is already game over.
         println!("GameOver (Eval: {})", eval);
@@ -123,11 +243,22 @@ fn print_eval(mv: u8, eval: i8) {
         // we want to output the columns in [1-7].
         println!("Best Move: {} (Eval: {})", mv + 1, eval);
     }
+
+    if !pv.is_empty() {
+        println!("PV: {}", format_pv(pv));
+    }
+}
+
+/// formats a sequence of 0-indexed columns (e.g. a principal variation) as space-separated
+/// columns in [1-7].
+fn format_pv(pv: &[u8]) -> String {
+    pv.iter().map(|c| (c + 1).to_string()).collect::<Vec<_>>().join(" ")
 }
 
 
-/// plays the game from the given position.
-fn play_position(position: Option<&str>) -> Result<()> {
+/// plays the game from the given position, per `cli`'s `--movetime`/`--tt-load`/`--tt-save`/
+/// `--book` flags.
+fn play_position(position: Option<&str>, cli: &Cli) -> Result<()> {
     let mut board;
     let mut pos_str;
 
@@ -140,13 +271,11 @@ fn play_position(position: Option<&str>) -> Result<()> {
         board = Board::new();
     }
 
-    let mut explorer = Explorer::new();
-
     loop {
         println!("{}\n{}\n--------------------------------", board, pos_str);
 
         println!("Waiting for engine to generate move...");
-        let (mv, eval) = explorer.solve(&board);
+        let (mv, eval, pv) = solve_with_cli(&board, cli)?;
 
         if mv == EMPTY_MOVE { // used when the given game is already over.
             break;
@@ -159,6 +288,9 @@ fn play_position(position: Option<&str>) -> Result<()> {
         else {
             let mv_played = mv + 1;
             println!("Engine played {} (Eval {})", mv_played, eval);
+            if !pv.is_empty() {
+                println!("PV: {}", format_pv(&pv));
+            }
             pos_str.push_str(&format!("{}", mv_played));
         }
 
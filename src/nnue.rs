@@ -1,16 +1,16 @@
 // Connect4EngineRust, a strong solver for the connect-4 board game.
 // Copyright (C) 2023 Derick Tseng
-// 
+//
 // This program is free software: you can redistribute it and/or modify
 // it under the terms of the GNU General Public License as published by
 // the Free Software Foundation, either version 3 of the License, or
 // (at your option) any later version.
-// 
+//
 // This program is distributed in the hope that it will be useful,
 // but WITHOUT ANY WARRANTY; without even the implied warranty of
 // MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
 // GNU General Public License for more details.
-// 
+//
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
@@ -18,65 +18,117 @@ use std::path::Path;
 use tch::{Tensor, Device, CModule, nn::Module};
 use anyhow::Result;
 
-use crate::board::Position;
+use crate::board::{Board, Position};
 
 const BOARD_BITS: usize = 48;
 const FEATURES: usize = 2 * BOARD_BITS + 1 + 1;
+const P2MV_ROW: i64 = (FEATURES - 2) as i64;
+const MOVES_ROW: i64 = (FEATURES - 1) as i64;
+
+/// one entry of the accumulator undo stack: the first-layer weight rows touched by a `push`, and
+/// the signed coefficient each was scaled by, so `pop` can subtract exactly what `push` added.
+type AccumulatorDelta = [(i64, f32); 3];
 
 #[derive(Debug)]
 pub struct Nnue {
-    /// the network to use to evaluate.
+    /// the network to use to evaluate, applied to the already-accumulated first layer.
     net: CModule,
 
-    /// array to construct the tensor.
-    tensor_arr: [f32; FEATURES],
+    /// first-layer weight matrix, used to update the accumulator incrementally instead of
+    /// rebuilding all `FEATURES` inputs on every move.
+    w1: Tensor,
+
+    /// persistent first-layer pre-activation sum. `push`/`pop` keep this in sync with the board's
+    /// `play`/`revert` so that evaluating the next position costs one weight-column update per
+    /// changed feature instead of recomputing the whole input layer.
+    accumulator: Tensor,
+
+    /// side-to-move parity of the position the accumulator currently represents, toggled by every
+    /// `push`/`pop` so they don't need it passed in explicitly.
+    to_move: u8,
 
-    /// the tensor to input.
-    tensor: Tensor
+    /// undo stack of per-`push` deltas, so `pop` can restore the accumulator exactly without
+    /// needing to know which move it is undoing.
+    history: Vec<AccumulatorDelta>,
 }
 
 
 impl Nnue {
-    /// Loads a new network from a file.
+    /// Loads a new network from a file and starts the accumulator at the empty-board position.
     pub fn new(modelfile: &Path, device: Device) -> Result<Self> {
         let net = tch::jit::CModule::load_on_device(modelfile, device)?;
-        let tensor = Tensor::new();
-        let tensor_arr = [0.; FEATURES];
+        let w1 = net.method_ts("first_layer_weight", &[])?;
+        let accumulator = Tensor::zeros(&[w1.size()[1]], (tch::Kind::Float, device));
 
-        Ok(Self { net, tensor, tensor_arr })
+        Ok(Self { net, w1, accumulator, to_move: 0, history: Vec::new() })
     }
 
-    fn update(
-        &mut self,
-        p0: Position,
-        p1: Position,
-        p2mv: u8,
-        moves: u32) {
+    /// rebuilds the accumulator from scratch for `board`. Used whenever `Explorer` jumps to a
+    /// position that isn't reachable by replaying `push`/`pop` from the current one (e.g. a fresh
+    /// root), since the incremental updates only make sense along a single line of play.
+    pub fn refresh(&mut self, board: &Board) {
+        let player = board.get_curr_player_pos();
+        let opponent = board.get_opp_player_pos();
+        let moves = board.moves_played();
+        let p2mv = (moves % 2) as u8;
 
+        let (p0, p1) = if p2mv == 0 { (player, opponent) } else { (opponent, player) };
+
+        let mut features = [0f32; FEATURES];
         for i in 0..BOARD_BITS {
-            self.tensor_arr[i] = ((p0 >> i) & 1) as f32;
+            features[i] = ((p0 >> i) & 1) as f32;
         }
-        for (sh, i) in (BOARD_BITS..2*BOARD_BITS).enumerate() {
-            self.tensor_arr[i] = ((p1 >> sh) & 1) as f32;
+        for (sh, i) in (BOARD_BITS..2 * BOARD_BITS).enumerate() {
+            features[i] = ((p1 >> sh) & 1) as f32;
         }
+        features[FEATURES - 2] = p2mv as f32;
+        features[FEATURES - 1] = moves as f32;
+
+        self.accumulator = Tensor::of_slice(&features).matmul(&self.w1);
+        self.to_move = p2mv;
+        self.history.clear();
+    }
 
-        self.tensor_arr[FEATURES - 2] = p2mv as f32;
-        self.tensor_arr[FEATURES - 1] = moves as f32;
+    /// incrementally applies the single stone placed at `pos` in column `col`, mirroring
+    /// `Board::play(pos)`. Only the weight column for the newly set bit (plus the side-to-move and
+    /// move-count feature rows, which change every move) are folded into the accumulator, rather
+    /// than recomputing all `FEATURES` inputs.
+    pub fn push(&mut self, pos: Position, col: u8) {
+        debug_assert_eq!(Board::pos_to_col(pos), col);
 
-        self.tensor = Tensor::of_slice(&self.tensor_arr);
+        let bit = pos.trailing_zeros() as i64;
+        // the stone being placed belongs to the side to move *before* this push; the feature
+        // plane for that side is p0 if `to_move == 0`, else p1.
+        let stone_row = if self.to_move == 0 { bit } else { BOARD_BITS as i64 + bit };
+        let p2mv_delta = if self.to_move == 0 { 1.0 } else { -1.0 };
+
+        let delta: AccumulatorDelta = [(stone_row, 1.0), (P2MV_ROW, p2mv_delta), (MOVES_ROW, 1.0)];
+        self.apply(&delta);
+        self.history.push(delta);
+
+        self.to_move ^= 1;
     }
 
+    /// undoes the effect of the most recent `push`, mirroring `Board::revert`.
+    pub fn pop(&mut self) {
+        let delta = self.history.pop().expect("pop called without a matching push");
+        self.to_move ^= 1;
 
-    pub fn evaluate(
-        &mut self,
-        p0: Position,
-        p1: Position,
-        p2mv: u8,
-        moves: u32) -> isize {
+        for &(row, coeff) in &delta {
+            self.accumulator -= self.w1.select(0, row) * coeff;
+        }
+    }
 
-        self.update(p0, p1, p2mv, moves);
-        let value = f32::from(self.net.forward(&self.tensor));
+    /// applies a `push`'s feature deltas to the accumulator.
+    fn apply(&mut self, delta: &AccumulatorDelta) {
+        for &(row, coeff) in delta {
+            self.accumulator += self.w1.select(0, row) * coeff;
+        }
+    }
 
-        return value.round() as isize
+    /// evaluates the position the accumulator currently represents.
+    pub fn evaluate(&self) -> isize {
+        let value = f32::from(self.net.forward(&self.accumulator));
+        value.round() as isize
     }
 }
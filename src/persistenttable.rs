@@ -0,0 +1,301 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::mem::size_of;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use memmap2::{Mmap, MmapOptions};
+use rand::seq::SliceRandom;
+
+use crate::board::{Board, SIZE};
+use crate::moves::Moves;
+use crate::strategy::Explorer;
+use crate::transpositiontable::{Flag, FLAG_EXACT};
+
+/// magic bytes identifying a persistent table file, so `load` can fail fast on a foreign file.
+const MAGIC: u64 = 0x4334_5046_4C41_5432; // "C4PFLAT2", ascii-ish.
+
+/// number of consecutive slots scanned together when probing (a "group"). Each slot in a group
+/// carries a one-byte control tag so the hot probe loop can reject most non-matches with a cheap
+/// byte compare before paying for the full 64-bit key comparison.
+const GROUP_SIZE: usize = 8;
+
+/// fixed-size on-disk record: the full position key, the solved evaluation, its flag (this table
+/// only ever stores `FLAG_EXACT` entries), the best move, and a one-byte hash tag used for the
+/// fast group scan.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Record {
+    key: u64,
+    eval: i8,
+    flag: u8,
+    mv: u8,
+    tag: u8,
+    _pad: u8,
+}
+
+const RECORD_SIZE: usize = size_of::<Record>();
+
+/// fixed file header: magic, number of groups, hash seed.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Header {
+    magic: u64,
+    num_groups: u64,
+    hash_seed: u64,
+}
+
+const HEADER_SIZE: usize = size_of::<Header>();
+
+/// A persistent, memory-mapped transposition table.
+///
+/// Unlike `TranspositionTable`, which lives entirely in memory for the duration of one search,
+/// `PersistentTable` is a flat file on disk: a fixed header (magic, group count, hash seed)
+/// followed by a contiguous array of fixed-size records, grouped `GROUP_SIZE` at a time. It is
+/// meant to hold a large precomputed table of shallow positions (an opening book, or a checkpoint
+/// of a long solve) that can be memory-mapped and probed across runs without re-solving, rather
+/// than being rebuilt from scratch every time the solver starts.
+pub struct PersistentTable {
+    mmap: Mmap,
+    num_groups: u64,
+    hash_seed: u64,
+}
+
+impl fmt::Debug for PersistentTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PersistentTable").field("num_groups", &self.num_groups).finish()
+    }
+}
+
+impl PersistentTable {
+    /// opens and memory-maps a table previously written by `build`/`build_endgame`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file)? };
+
+        if mmap.len() < HEADER_SIZE {
+            bail!("persistent table file is too small to contain a header");
+        }
+
+        let header = unsafe { *(mmap.as_ptr() as *const Header) };
+        if header.magic != MAGIC {
+            bail!("not a connect4engine persistent table file");
+        }
+
+        Ok(Self { mmap, num_groups: header.num_groups, hash_seed: header.hash_seed })
+    }
+
+    /// probes the table for `key`, returning `(eval, mv)` on an exact hit.
+    pub fn get(&self, key: u64) -> Option<(i8, u8)> {
+        let group = (Self::hash(key, self.hash_seed) % self.num_groups) as usize;
+        let tag = Self::tag(key, self.hash_seed);
+        let group_start = HEADER_SIZE + group * GROUP_SIZE * RECORD_SIZE;
+
+        for slot in 0..GROUP_SIZE {
+            let offset = group_start + slot * RECORD_SIZE;
+            let record = unsafe { *(self.mmap.as_ptr().add(offset) as *const Record) };
+
+            // cheap one-byte compare first; only pay for the full key comparison on a tag hit,
+            // which keeps the hot probe loop cache-friendly.
+            if record.tag != tag {
+                continue;
+            }
+            if record.flag == FLAG_EXACT && record.key == key {
+                return Some((record.eval, record.mv));
+            }
+        }
+
+        None
+    }
+
+    /// solves every reachable position up to `max_ply` moves and writes the result to `path` as a
+    /// flat, memory-mappable table. This is what the `DB book` CLI subcommand drives.
+    pub fn build(path: &Path, max_ply: u8) -> Result<()> {
+        let mut explorer = Explorer::new();
+        let mut records = Vec::new();
+        let hash_seed = 0x9E37_79B9_7F4A_7C15; // fxhash-style odd multiplier.
+
+        Self::solve_into(&mut explorer, Board::new(), max_ply, hash_seed, &mut records);
+        Self::write_table(path, &records, hash_seed)
+    }
+
+    /// builds a sampled endgame tablebase: repeatedly plays a random legal (non-losing, where
+    /// possible) game out from the empty board until at most `max_empty` cells remain empty,
+    /// solves that position exactly via `Explorer::solve`, and records it, continuing until
+    /// `num_positions` distinct positions have been collected. Serialized the same way `build`'s
+    /// opening book is, so `PersistentTable::load` and `get` work unchanged on the result; see the
+    /// `DB endgame-book` CLI subcommand and `Explorer::with_endgame_table`.
+    ///
+    /// This deliberately samples rather than exhaustively enumerating every position with `<=
+    /// max_empty` empty cells the way a textbook retrograde analysis would: even a modest
+    /// `max_empty` still leaves tens of plies of game tree between the empty board and that zone,
+    /// and Connect 4's distinct-position count at that depth is in the trillions - the same reason
+    /// real engines lean on forward alpha-beta search rather than a precomputed table for anything
+    /// but the last handful of plies. Sampling representative positions instead gives `search` a
+    /// useful set of instant hits without that intractable enumeration.
+    pub fn build_endgame(path: &Path, max_empty: u8, num_positions: usize) -> Result<()> {
+        let mut explorer = Explorer::new();
+        let mut records = Vec::new();
+        let mut visited: HashSet<u64> = HashSet::new();
+        let hash_seed = 0x9E37_79B9_7F4A_7C15;
+
+        // if the reachable distinct-position space at `max_empty` is smaller than
+        // `num_positions` (e.g. `max_empty == 0`, which only has a handful of final-move
+        // positions), every fresh sample eventually collides with `visited` forever. Bail out
+        // once misses run this far ahead of hits rather than spinning forever.
+        const MAX_CONSECUTIVE_MISSES: usize = 1_000_000;
+        let mut consecutive_misses = 0;
+
+        let target_moves = SIZE.saturating_sub(max_empty);
+        while records.len() < num_positions {
+            let board = Self::random_near_terminal_board(target_moves);
+            let key = board.get_unique_position_key();
+
+            // a finished game has no move to record (mirrors `solve_into`'s skip), and a key
+            // already in `visited` was already sampled from a different random line.
+            if board.is_game_over() || !visited.insert(key) {
+                consecutive_misses += 1;
+                if consecutive_misses >= MAX_CONSECUTIVE_MISSES {
+                    bail!(
+                        "build_endgame stalled: {} consecutive samples produced no new distinct \
+                         position after collecting {}/{} (max_empty={} likely has fewer reachable \
+                         positions than num_positions)",
+                        MAX_CONSECUTIVE_MISSES, records.len(), num_positions, max_empty,
+                    );
+                }
+                continue;
+            }
+            consecutive_misses = 0;
+
+            let (mv, eval) = explorer.solve(&board);
+            records.push(Record {
+                key,
+                eval,
+                flag: FLAG_EXACT as Flag,
+                // `get_unique_position_key` may have collapsed `board` onto its mirror image's
+                // key, so the move must be canonicalized to that shared orientation before
+                // storing - see `Board::canonicalize_col` and `probe_book`/`PersistentTable::get`
+                // on the read side.
+                mv: board.canonicalize_col(mv),
+                tag: Self::tag(key, hash_seed),
+                _pad: 0,
+            });
+        }
+
+        Self::write_table(path, &records, hash_seed)
+    }
+
+    /// plays random non-losing moves from the empty board until either `target_moves` plies have
+    /// been played or the game ends first, whichever comes first - mirrors
+    /// `Database::generate_random_board`'s technique for generating representative sample
+    /// positions rather than exhaustively enumerating them.
+    fn random_near_terminal_board(target_moves: u8) -> Board {
+        let mut board = Board::new();
+
+        while board.moves_played() < target_moves as u32 && !board.is_game_over() {
+            let possible = board.possible_moves();
+            let non_losing = board.non_losing_moves(possible);
+            let next_moves: Vec<_> = if non_losing == 0 {
+                Moves::new(possible).collect()
+            } else {
+                Moves::new(non_losing).collect()
+            };
+
+            let (mv_pos, _) = next_moves.choose(&mut rand::thread_rng()).unwrap();
+            board.play(*mv_pos);
+        }
+
+        board
+    }
+
+    /// writes `records`, grouped by hash into `GROUP_SIZE`-sized buckets, to `path` in
+    /// `PersistentTable`'s on-disk format. Shared by `build` and `build_endgame`.
+    fn write_table(path: &Path, records: &[Record], hash_seed: u64) -> Result<()> {
+        // size the group table so it holds the records with room to spare; a prime-ish odd number
+        // of groups keeps the hash spread out, mirroring `TranspositionTable::MAX_TABLE_SIZE`.
+        let mut num_groups = u64::max(1, (records.len() as u64 / GROUP_SIZE as u64) * 2 + 1);
+        let buckets = loop {
+            let mut buckets: Vec<Vec<Record>> = vec![Vec::new(); num_groups as usize];
+            for record in records {
+                let group = (Self::hash(record.key, hash_seed) % num_groups) as usize;
+                // the same position may have been solved more than once (e.g. via transposition).
+                if !buckets[group].iter().any(|r| r.key == record.key) {
+                    buckets[group].push(*record);
+                }
+            }
+            // `get` only scans a key's own group, so grow the table until no group overflows.
+            if buckets.iter().all(|bucket| bucket.len() <= GROUP_SIZE) {
+                break buckets;
+            }
+            num_groups = num_groups * 2 + 1;
+        };
+
+        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(path)?;
+        let header = Header { magic: MAGIC, num_groups, hash_seed };
+        file.write_all(unsafe {
+            std::slice::from_raw_parts(&header as *const Header as *const u8, HEADER_SIZE)
+        })?;
+
+        let empty = Record { key: 0, eval: 0, flag: 0, mv: 0, tag: 0, _pad: 0 };
+        for bucket in buckets {
+            for slot in 0..GROUP_SIZE {
+                let record = bucket.get(slot).copied().unwrap_or(empty);
+                file.write_all(unsafe {
+                    std::slice::from_raw_parts(&record as *const Record as *const u8, RECORD_SIZE)
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// recursively enumerates every position reachable within `max_ply` moves, solving each one
+    /// and appending its exact evaluation to `records`.
+    fn solve_into(
+        explorer: &mut Explorer,
+        board: Board,
+        remaining_ply: u8,
+        hash_seed: u64,
+        records: &mut Vec<Record>) {
+
+        if board.is_game_over() {
+            return;
+        }
+
+        let key = board.get_unique_position_key();
+        let (mv, eval) = explorer.solve(&board);
+        records.push(Record {
+            key,
+            eval,
+            flag: FLAG_EXACT as Flag,
+            // see `build_endgame`'s identical comment: `board`'s own orientation may not be the
+            // one `key` canonicalized to, so the move needs remapping to match.
+            mv: board.canonicalize_col(mv),
+            tag: Self::tag(key, hash_seed),
+            _pad: 0,
+        });
+
+        if remaining_ply == 0 {
+            return;
+        }
+
+        for (m, _) in Moves::new(board.possible_moves()) {
+            let mut next = board;
+            next.play(m);
+            Self::solve_into(explorer, next, remaining_ply - 1, hash_seed, records);
+        }
+    }
+
+    /// fxhash-style multiplicative hash of a 64-bit position key (see
+    /// `Board::get_unique_position_key`).
+    fn hash(key: u64, seed: u64) -> u64 {
+        (key.wrapping_mul(seed)).rotate_left(5) ^ key
+    }
+
+    /// derives the one-byte control tag stored alongside each record for the fast group scan.
+    fn tag(key: u64, seed: u64) -> u8 {
+        (Self::hash(key, seed) >> 56) as u8
+    }
+}
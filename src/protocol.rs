@@ -0,0 +1,150 @@
+// Connect4EngineRust, a strong solver for the connect-4 board game.
+// Copyright (C) 2023 Derick Tseng
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Line-based stdin/stdout command protocol for driving an `Explorer` without linking against the
+//! crate directly, in the spirit of the Poly Checkers Interface: a GUI or test harness speaks a
+//! small set of plain-text commands over stdin, and reads replies back from stdout.
+//!
+//! Supported commands, one per line:
+//! * `isready` - replies `readyok` once the engine is ready to accept further commands.
+//! * `newgame` - resets the position and transposition table to a fresh game.
+//! * `move <col> [<col> ...]` - plays one or more column drops (1-indexed, matching the rest of
+//!   the crate's user-facing move convention) onto the current position.
+//! * `go depth <n>` - searches the current position `n` plies deep (see `Explorer::go_depth`) and
+//!   replies `bestmove <col> (eval <score>)` (column 1-indexed), or `bestmove none (eval <score>)`
+//!   if the game is already over.
+//! * `eval` - replies `eval <score>` with the current position's evaluation, without searching
+//!   for or playing a move.
+//!
+//! An unrecognized command or an illegal move gets an `error <message>` reply rather than ending
+//! the loop, so a caller can recover and keep the session alive.
+
+use std::io::{BufRead, Write};
+use anyhow::Result;
+
+use crate::board::Board;
+use crate::moves::EMPTY_MOVE;
+use crate::strategy::Explorer;
+
+/// runs the command loop, reading lines from `input` and writing replies to `output` until
+/// `input` reaches EOF. See the module documentation for the supported commands.
+pub fn run<R: BufRead, W: Write>(input: R, mut output: W) -> Result<()> {
+    let mut board = Board::new();
+    let mut explorer = Explorer::new();
+
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut tokens = line.split_whitespace();
+        let command = tokens.next().unwrap_or("");
+
+        match command {
+            "isready" => writeln!(output, "readyok")?,
+            "newgame" => {
+                board = Board::new();
+                explorer = Explorer::new();
+            }
+            "move" => handle_move(&mut board, tokens, &mut output)?,
+            "go" => handle_go(&board, &mut explorer, tokens, &mut output)?,
+            "eval" => {
+                let eval = explorer.evaluate(&board);
+                writeln!(output, "eval {}", eval)?;
+            }
+            _ => writeln!(output, "error unknown command {}", command)?,
+        }
+
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+/// applies one or more `move <col> ...` column drops (1-indexed) to `board`, stopping at (and
+/// reporting) the first illegal one rather than silently applying only a partial prefix.
+fn handle_move<'a, W: Write>(
+    board: &mut Board,
+    cols: impl Iterator<Item = &'a str>,
+    output: &mut W,
+) -> Result<()> {
+    let mut saw_token = false;
+
+    for tok in cols {
+        saw_token = true;
+
+        let col = tok.parse::<u8>().ok().and_then(|c| c.checked_sub(1));
+        let col = match col {
+            Some(col) => col,
+            None => {
+                writeln!(output, "error invalid column {}", tok)?;
+                return Ok(());
+            }
+        };
+
+        if let Err(e) = board.add(col) {
+            writeln!(output, "error {}", e)?;
+            return Ok(());
+        }
+    }
+
+    if !saw_token {
+        writeln!(output, "error move requires at least one column")?;
+    }
+
+    Ok(())
+}
+
+/// handles `go depth <n>`, searching `board` via `Explorer::go_depth` and replying with
+/// `bestmove <col> (eval <score>)` (column 1-indexed), or `bestmove none (eval <score>)` if the
+/// game is already over.
+fn handle_go<'a, W: Write>(
+    board: &Board,
+    explorer: &mut Explorer,
+    mut args: impl Iterator<Item = &'a str>,
+    output: &mut W,
+) -> Result<()> {
+    match args.next() {
+        Some("depth") => (),
+        Some(other) => {
+            writeln!(output, "error unknown go subcommand {}", other)?;
+            return Ok(());
+        }
+        None => {
+            writeln!(output, "error go requires a subcommand")?;
+            return Ok(());
+        }
+    }
+
+    let depth = match args.next().and_then(|s| s.parse::<u8>().ok()) {
+        Some(depth) => depth,
+        None => {
+            writeln!(output, "error go depth requires a numeric depth")?;
+            return Ok(());
+        }
+    };
+
+    let (mv, eval) = explorer.go_depth(board, depth);
+    if mv == EMPTY_MOVE {
+        writeln!(output, "bestmove none (eval {})", eval)?;
+    } else {
+        writeln!(output, "bestmove {} (eval {})", mv + 1, eval)?;
+    }
+
+    Ok(())
+}
@@ -15,8 +15,34 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::mem::MaybeUninit;
+use rand::Rng;
 use crate::board::{Position, WIDTH};
 
+/// engine strength for `ScoredMoves::pick`/`pick_weighted`: restricts move selection to a window
+/// of the top-k highest-scored moves, so a casual opponent can be given a human-playable bot
+/// without a separate search configuration - smaller windows play closer to the objectively best
+/// move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    /// draws only from the single best-scored move.
+    Hard,
+    /// draws from the top 2 scored moves.
+    Normal,
+    /// draws from the top 3 scored moves.
+    Easy,
+}
+
+impl Difficulty {
+    /// size of the top-k window this difficulty draws from.
+    fn window_size(self) -> usize {
+        match self {
+            Difficulty::Hard => 1,
+            Difficulty::Normal => 2,
+            Difficulty::Easy => 3,
+        }
+    }
+}
+
 /// a Moves-like iterator but with the moves stored from low scores to high.
 /// Higher scores are returned from the iterator before lower ones.
 #[derive(Clone)]
@@ -50,6 +76,25 @@ impl<T> ScoredMoves<T> where T: std::marker::Copy + std::cmp::PartialOrd {
         Self { move_scores, size, ptr }
     }
 
+    /// adds a move, boosting `score` with a dynamic move-ordering signal before inserting it:
+    /// `killer_bonus` if `col` is one of the current ply's killer moves (a move that recently
+    /// caused a beta cutoff at this depth), otherwise `history_score` (how often `col` has caused
+    /// cutoffs overall). This ranks killer/history-derived ordering below the transposition-table
+    /// refutation move but above the plain static `score`.
+    pub fn add_with_context(
+        &mut self,
+        mv: Position,
+        col: u8,
+        score: T,
+        is_killer: bool,
+        killer_bonus: T,
+        history_score: T)
+    where T: std::ops::Add<Output = T> {
+
+        let boosted = score + if is_killer { killer_bonus } else { history_score };
+        self.add(mv, col, boosted);
+    }
+
     /// adds a new move, col, score triple in order.
     pub fn add(&mut self, mv: Position, col: u8, score: T) {
         let mut i = self.size;
@@ -71,6 +116,60 @@ impl<T> ScoredMoves<T> where T: std::marker::Copy + std::cmp::PartialOrd {
 
         self.move_scores[i].write((mv, col, score));
     }
+
+    /// picks a move uniformly at random from `difficulty`'s top-k window of the highest-scored
+    /// moves added so far (see `Difficulty`). Returns `None` if no moves have been added.
+    pub fn pick<R: Rng + ?Sized>(&self, difficulty: Difficulty, rng: &mut R) -> Option<(Position, u8)> {
+        let window = self.window(difficulty)?;
+        let (mv, col, _) = window[rng.gen_range(0..window.len())];
+        Some((mv, col))
+    }
+
+    /// like `pick`, but draws from the top-k window weighted by each entry's score (converted via
+    /// `Into<f64>`) rather than uniformly, so a higher-scored move within the window is more
+    /// likely to be chosen. Falls back to `pick`'s uniform behavior if every weight in the window
+    /// is non-positive, since otherwise every move in it would be unreachable.
+    pub fn pick_weighted<R: Rng + ?Sized>(&self, difficulty: Difficulty, rng: &mut R) -> Option<(Position, u8)>
+    where T: Into<f64> {
+
+        let window = self.window(difficulty)?;
+        let weights: Vec<f64> = window.iter().map(|&(_, _, score)| f64::max(score.into(), 0.0)).collect();
+        let total: f64 = weights.iter().sum();
+
+        if total <= 0.0 {
+            return self.pick(difficulty, rng);
+        }
+
+        let mut roll = rng.gen_range(0.0..total);
+        for (&(mv, col, _), &weight) in window.iter().zip(weights.iter()) {
+            if roll < weight {
+                return Some((mv, col));
+            }
+            roll -= weight;
+        }
+
+        // floating-point rounding may leave a sliver of `roll` unconsumed; fall back to the
+        // window's last (lowest-scored) entry rather than panicking.
+        window.last().map(|&(mv, col, _)| (mv, col))
+    }
+
+    /// the initialized entries within `difficulty`'s top-k window, or `None` if no moves have
+    /// been added.
+    fn window(&self, difficulty: Difficulty) -> Option<&[(Position, u8, T)]> {
+        if self.size == 0 {
+            return None;
+        }
+
+        let window_size = usize::min(difficulty.window_size(), self.size);
+
+        // SAFETY: indices [0, self.size) of `move_scores` are always initialized, by the same
+        // invariant `Iterator::next` relies on below.
+        let initialized: &[(Position, u8, T)] = unsafe {
+            std::slice::from_raw_parts(self.move_scores.as_ptr() as *const (Position, u8, T), self.size)
+        };
+
+        Some(&initialized[..window_size])
+    }
 }
 
 impl<T> Iterator for ScoredMoves<T> where T: std::marker::Copy {
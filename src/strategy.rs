@@ -14,18 +14,39 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
 use crate::scoredmoves::ScoredMoves;
-use crate::transpositiontable::{TranspositionTable, FLAG_UPPER, FLAG_LOWER, FLAG_EXACT};
+use crate::persistenttable::PersistentTable;
+use crate::transpositiontable::{Entry, TranspositionTable, FLAG_UPPER, FLAG_LOWER, FLAG_EXACT};
 use crate::moves::{EMPTY_MOVE, Moves};
-use crate::board::{SIZE, Board, Position};
+use crate::board::{SIZE, WIDTH, HEIGHT, COUNTS_PER_COL, Board, Position};
 
 pub const MAX_SCORE: i8 = 2 + SIZE as i8;
 pub const TIE_SCORE: i8 = 0;
 pub const PV_SIZE: usize = SIZE as usize;
 const REFUTATION_SCORE: i8 = 16;
 
-// Evaluation table for number of possible 4-in-a-rows
-/*
+/// at or below this many empty cells remaining, `search` hands off to `search_endgame` instead.
+/// Profiling shows the overwhelming majority of nodes live in these shallow subtrees, so
+/// eliminating their per-node transposition-table hashing and move-ordering bookkeeping has an
+/// outsized effect on throughput without changing any returned eval. Tunable.
+const ENDGAME_CELL_THRESHOLD: u8 = 8;
+
+/// how often (in nodes explored) a worker checks the shared stop flag. Checking every node would
+/// add an atomic load to the hottest loop in the engine, so we only poll periodically.
+const STOP_POLL_INTERVAL: usize = 1024;
+
+/// a `TranspositionTable` reachable from multiple search threads at once. The table itself is
+/// internally lock-free, so this is a plain `Arc` rather than a `Mutex`-wrapped one.
+type SharedTable = Arc<TranspositionTable>;
+
+// Evaluation table for number of possible 4-in-a-rows, laid out the same way `Board`'s `Display`
+// impl prints the board (top row first), and used by `heuristic_eval` below.
 pub const EVALTABLE: [i16; SIZE as usize] = [
     3, 4, 5,  7,  5,  4, 3,
     4, 6, 8,  10, 8,  6, 4,
@@ -34,34 +55,195 @@ pub const EVALTABLE: [i16; SIZE as usize] = [
     4, 6, 8,  10, 8,  6, 4,
     3, 4, 5,  7,  5,  4, 3
 ];
-*/
 
 #[derive(Debug)]
 pub struct Explorer {
     /// number of nodes this explorer has searched.
     nodes_explored: usize,
 
-    /// transposition table used by the explorer.
-    transpositiontable: TranspositionTable,
+    /// transposition table used by the explorer. Shared so that multiple `Explorer`s can search
+    /// the same tree in parallel (Lazy SMP); a lone explorer simply owns its table exclusively.
+    transpositiontable: SharedTable,
+
+    /// shared flag polled periodically during search so a Lazy SMP worker can be told to stop as
+    /// soon as a sibling worker has resolved the root.
+    stop: Arc<AtomicBool>,
 
     /// number of moves that failed low.
     fail_low_nodes: usize,
 
     /// number of moves that failed high.
-    fail_high_nodes: usize
+    fail_high_nodes: usize,
+
+    /// small per-worker jitter added to move ordering so that Lazy SMP workers searching the same
+    /// root diverge into different subtrees instead of all following the identical principal
+    /// variation. Zero for a lone, non-parallel explorer.
+    perturbation: i8,
+
+    /// killer-move table: up to 2 moves per ply that most recently caused a beta cutoff at that
+    /// depth, tried early the next time that ply is searched.
+    killers: [[u8; 2]; PV_SIZE],
+
+    /// history heuristic: how often each column has caused a beta cutoff across the whole search,
+    /// used as a secondary move-ordering signal below the killer moves.
+    history: [u32; WIDTH as usize],
+
+    /// wall-clock deadline for the current search, if time-bounded (see `solve_timed`). Checked
+    /// at the same interval as `stop`, and sets `stop` once passed so the cutoff is observed
+    /// immediately everywhere `stop` already is.
+    deadline: Option<Instant>,
+
+    /// dedicated transposition table for `search_depth`/`go_depth`, kept separate from
+    /// `transpositiontable`: `search_depth` stores `depth` as a countdown to the depth limit
+    /// rather than moves-played, and can plant `FLAG_EXACT` entries holding only a clamped
+    /// `heuristic_eval` estimate rather than a proven score, neither of which `search`'s exact-score
+    /// fast path is safe to trust. Cleared by `go_depth` on every call rather than accumulated, so
+    /// its stale depth semantics (countdown from whatever `max_depth` the last call used) are never
+    /// an issue across calls.
+    depth_table: TranspositionTable,
+
+    /// optional precomputed opening book (see `PersistentTable`), probed in `solve`/`solve_timed`
+    /// before the board is actually searched so a hit on a previously-solved position returns
+    /// instantly.
+    book: Option<Arc<PersistentTable>>,
+
+    /// optional precomputed endgame tablebase (see `PersistentTable::build_endgame`), probed by
+    /// `search` on every node so an exact hit short-circuits the remaining lookahead instead of
+    /// solving the same near-terminal position from scratch every time it's reached.
+    endgame_table: Option<Arc<PersistentTable>>,
+
+    /// optional channel that receives a `SearchInfo` after every iteration of `evaluate_board`'s
+    /// aspiration-window ladder, so a caller (see `AnalysisWorker`) can observe the search
+    /// converging instead of only seeing the final answer. See `set_progress`.
+    progress: Option<Sender<SearchInfo>>,
 }
 
+/// one iteration's worth of progress out of `evaluate_board`'s aspiration-window ladder: each
+/// iteration narrows the window around the true evaluation, so `eval`, `nodes`, and `pv` all
+/// refine across successive `SearchInfo`s for the same search. Streamed via `Explorer::set_progress`.
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    /// which iteration of the aspiration-window ladder this is, starting at 1.
+    pub iteration: u32,
+
+    /// fail-soft evaluation as of this iteration; only exact once the final iteration arrives.
+    pub eval: i8,
+
+    /// total nodes this explorer has explored so far (cumulative: not reset per iteration, nor
+    /// between searches run on the same explorer).
+    pub nodes: usize,
+
+    /// principal variation from the root as of this iteration, as 0-indexed columns.
+    pub pv: Vec<u8>,
+}
+
+/// move-ordering bonus for a killer-table hit; ranks above the static ordering function but below
+/// the transposition-table refutation move.
+const KILLER_BONUS: i8 = 12;
+
 impl Explorer {
     pub fn new() -> Self {
+        let transpositiontable = Arc::new(TranspositionTable::new());
+        let stop = Arc::new(AtomicBool::new(false));
+        Self::with_shared_state(transpositiontable, stop)
+    }
+
+    /// creates an explorer that searches against an already-shared transposition table and stop
+    /// flag, for use as one worker of a Lazy SMP search.
+    fn with_shared_state(transpositiontable: SharedTable, stop: Arc<AtomicBool>) -> Self {
         let nodes_explored = 0;
-        let transpositiontable = TranspositionTable::new();
         let (fail_low_nodes, fail_high_nodes) = (0, 0);
-        Self { nodes_explored, transpositiontable, fail_low_nodes, fail_high_nodes }
+        Self {
+            nodes_explored, transpositiontable, stop, fail_low_nodes, fail_high_nodes,
+            perturbation: 0,
+            killers: [[EMPTY_MOVE; 2]; PV_SIZE],
+            history: [0; WIDTH as usize],
+            deadline: None,
+            depth_table: TranspositionTable::new(),
+            book: None,
+            endgame_table: None,
+            progress: None,
+        }
+    }
+
+    /// creates an explorer with its own fresh transposition table but a caller-supplied stop
+    /// flag, for use as the search half of an `AnalysisWorker`: the worker thread owns this
+    /// `Explorer` outright, but the flag is shared with the handle the caller keeps so a `Stop`
+    /// (or a `solve_timed` deadline) can interrupt the search from outside.
+    pub(crate) fn with_shared_stop(stop: Arc<AtomicBool>) -> Self {
+        Self::with_shared_state(Arc::new(TranspositionTable::new()), stop)
+    }
+
+    /// creates an explorer that searches against a pre-loaded transposition table (e.g. one
+    /// restored by `TranspositionTable::load`) instead of starting from an empty one.
+    pub fn with_table(transpositiontable: Arc<TranspositionTable>) -> Self {
+        Self::with_shared_state(transpositiontable, Arc::new(AtomicBool::new(false)))
+    }
+
+    /// attaches a precomputed opening book, probed before searching. See `book`.
+    pub fn with_book(mut self, book: Arc<PersistentTable>) -> Self {
+        self.book = Some(book);
+        self
+    }
+
+    /// attaches a precomputed endgame tablebase, probed on every search node. See `endgame_table`.
+    pub fn with_endgame_table(mut self, endgame_table: Arc<PersistentTable>) -> Self {
+        self.endgame_table = Some(endgame_table);
+        self
+    }
+
+    /// attaches (or, given `None`, clears) the channel that receives a `SearchInfo` after each
+    /// iteration of the next search's aspiration-window ladder. See `progress`.
+    pub fn set_progress(&mut self, progress: Option<Sender<SearchInfo>>) {
+        self.progress = progress;
+    }
+
+    /// sends a `SearchInfo` snapshot of the current iteration down `self.progress`, if attached.
+    /// A no-op (and in particular, doesn't pay for `principal_variation`'s TT walk) when nobody is
+    /// listening.
+    fn report_progress(&self, board: &Board, iteration: u32, eval: i8) {
+        if let Some(sender) = &self.progress {
+            let info = SearchInfo {
+                iteration, eval,
+                nodes: self.nodes_explored,
+                pv: self.principal_variation(board),
+            };
+            let _ = sender.send(info);
+        }
+    }
+
+    /// the transposition table this explorer has been searching into, for saving via
+    /// `TranspositionTable::save` once the caller is done with it.
+    pub fn transposition_table(&self) -> &TranspositionTable {
+        &self.transpositiontable
+    }
+
+    /// looks `board` up in the attached opening book, if any, returning `Some((move, eval))` on
+    /// an exact hit so the caller can skip searching entirely.
+    fn probe_book(&self, board: &Board) -> Option<(u8, i8)> {
+        let (eval, mv) = self.book.as_ref()?.get(board.get_unique_position_key())?;
+        Some((board.canonicalize_col(mv), eval))
+    }
+
+    /// looks `board` up in the attached endgame tablebase, if any, returning its exact evaluation
+    /// on a hit (discarding the recorded move, since `search` only needs the score).
+    fn probe_endgame_table(&self, board: &Board) -> Option<i8> {
+        let (eval, _mv) = self.endgame_table.as_ref()?.get(board.get_unique_position_key())?;
+        Some(eval)
+    }
+
+    /// runs `evaluate_board` with this worker's move-ordering jitter applied, derived from
+    /// `worker_id` so that each Lazy SMP worker explores a slightly different subtree first.
+    fn evaluate_board_perturbed(&mut self, board: &Board, worker_id: usize) -> i8 {
+        self.perturbation = (worker_id % WIDTH as usize) as i8;
+        self.evaluate_board(board, true)
     }
 
     /// returns the optimal move and evaluation for this explorer's current position.
     pub fn solve(&mut self, board: &Board) -> (u8, i8) {
-        // TODO - check if move is in openings database.
+        if let Some(hit) = self.probe_book(board) {
+            return hit;
+        }
 
         // needs to clear our transposition table first. Otherwise, we might store some nodes that
         // failed low, which are unusable for finding the principal variation.
@@ -72,7 +254,7 @@ impl Explorer {
             return (EMPTY_MOVE, eval);
         }
 
-        let pv = self.get_pv(board);
+        let pv = self.principal_variation(board);
         // println!("{:?}", pv);
 
         if !pv.is_empty() {
@@ -82,21 +264,224 @@ impl Explorer {
         panic!("Node not found in transposition table.")
     }
 
-    fn get_pv(&self, board: &Board) -> Vec<u8> {
+    /// solves `board` for each of its legal root moves independently, returning the `n` best
+    /// columns with their exact evaluations, sorted best-to-worst. This is the MultiPV facility
+    /// Stockfish exposes for analysis and strength limiting: a GUI can show move rankings, or a
+    /// caller can implement handicap play by choosing among near-best moves instead of always the
+    /// single optimum `solve` returns.
+    ///
+    /// The transposition table is cleared once up front, then shared across every root move's
+    /// search rather than being reused from (or cleared between) individual iterations, so later
+    /// moves benefit from cutoffs found while evaluating earlier ones.
+    pub fn solve_multipv(&mut self, board: &Board, n: usize) -> Vec<(u8, i8)> {
+        if board.is_game_over() {
+            return Vec::new();
+        }
+
+        let possible = board.possible_moves();
+        let winning_moves = board.player_win_moves(possible);
+        let moves_played = board.moves_played();
+
+        let start_max: i8 = i8::min(Explorer::win_eval(moves_played + 1), Explorer::win_eval(7));
+        let start_min: i8 = i8::max(-Explorer::win_eval(moves_played + 2), -Explorer::win_eval(8));
+
+        self.transpositiontable.clear();
+        self.reset_move_ordering_heuristics();
+
+        let mut results = Vec::new();
+        let mut boardcpy = *board;
+
+        for (m, c) in Moves::new(possible) {
+            let eval = if m & winning_moves != 0 {
+                // mirrors the endgame lookahead at the top of `search`: a move that wins outright
+                // is never itself handed to `search`, since the returned score is only exact for
+                // the player about to move, not the player who just won.
+                Explorer::win_eval(moves_played + 1)
+            } else {
+                boardcpy.play(m);
+                let val = -self.search(&boardcpy, -(start_max + 1), -(start_min - 1), Board::move_score);
+                boardcpy.revert(m);
+                val
+            };
+
+            results.push((c, eval));
+        }
+
+        results.sort_by(|a, b| b.1.cmp(&a.1));
+        results.truncate(n);
+        results
+    }
+
+    /// depth-limited, iterative-deepening alternative to `solve`: searches `board` to depth 1,
+    /// then 2, then 3, … up to `max_depth` plies ahead, reusing the transposition table and the
+    /// same wide aspiration window across iterations so each deeper pass benefits from the
+    /// previous one's move ordering instead of starting cold. Returns the best move found by the
+    /// deepest completed iteration, alongside its evaluation.
+    ///
+    /// Unlike `solve`, the returned evaluation is only exact if the game happens to end within
+    /// `max_depth` plies; otherwise it's `heuristic_eval`'s coarse positional estimate. This
+    /// trades solving to completion for a tunable time/strength knob - a fast approximate answer,
+    /// or (via a small `max_depth`) a deliberately weaker opponent.
+    pub fn go_depth(&mut self, board: &Board, max_depth: u8) -> (u8, i8) {
+        if board.is_game_over() {
+            return (EMPTY_MOVE, self.evaluate_board(board, false));
+        }
+
+        self.depth_table.clear();
+        self.reset_move_ordering_heuristics();
+
+        let start_max: i8 = i8::min(Explorer::win_eval(board.moves_played() + 1), Explorer::win_eval(7));
+        let start_min: i8 = i8::max(-Explorer::win_eval(board.moves_played() + 2), -Explorer::win_eval(8));
+
+        let mut eval = TIE_SCORE;
+        for depth in 1..=max_depth {
+            eval = self.search_depth(board, start_min - 1, start_max + 1, depth);
+        }
+
+        let mv = self.depth_table.get_entry(board).map_or_else(
+            || Explorer::one_ply_fallback_move(board),
+            |e| board.canonicalize_col(e.get_mv()));
+        (mv, eval)
+    }
+
+    /// one-ply lookahead used as a move fallback when `search_depth`'s own root call
+    /// short-circuited (immediate win, forced loss, or ran out of non-losing moves) without ever
+    /// writing a transposition-table entry for `board` itself, the same way `search`'s identical
+    /// short-circuits leave `solve`'s table lookups empty at the root - mirrors the fallback
+    /// `principal_variation` applies to extend a PV past the table's own reach.
+    fn one_ply_fallback_move(board: &Board) -> u8 {
+        let possible = board.possible_moves();
+
+        let winning_moves = board.player_win_moves(possible);
+        if winning_moves != 0 {
+            return Board::pos_to_col(winning_moves);
+        }
+
+        let losing_moves = board.opp_win_moves(possible);
+        if losing_moves != 0 {
+            return Board::pos_to_col(losing_moves);
+        }
+
+        Board::pos_to_col(possible)
+    }
+
+    /// solves `board` the same way `solve` does, but bounded by an optional wall-clock
+    /// `movetime`. The underlying search is already structured as a ladder of increasingly
+    /// precise aspiration windows (see `evaluate_board`); this lets that ladder run until
+    /// `movetime` elapses rather than to completion, at which point the shared stop flag is set
+    /// and the recursive search unwinds as soon as it next polls it (see `STOP_POLL_INTERVAL`).
+    ///
+    /// If interrupted, the returned move is still the best one on record in the transposition
+    /// table, but the returned evaluation is only the fail-soft bound in effect at the moment of
+    /// interruption rather than a proven score — the same caveat that applies to any alpha-beta
+    /// engine asked to report a result under time pressure.
+    pub fn solve_timed(&mut self, board: &Board, movetime: Option<Duration>) -> (u8, i8) {
+        if board.is_game_over() {
+            return (EMPTY_MOVE, self.evaluate_board(board, false));
+        }
+
+        if let Some(hit) = self.probe_book(board) {
+            return hit;
+        }
+
+        self.stop.store(false, Ordering::Relaxed);
+        self.deadline = movetime.map(|limit| Instant::now() + limit);
+
+        let eval = self.evaluate_board(board, true);
+        self.deadline = None;
+
+        let pv = self.principal_variation(board);
+        let mv = *pv.first().unwrap_or(&EMPTY_MOVE);
+        (mv, eval)
+    }
+
+    /// solves `board` using `threads` worker threads, all searching the same root position
+    /// against one shared transposition table (the Lazy SMP pattern). Each worker perturbs its
+    /// move ordering slightly so the threads diverge into different subtrees; a cutoff found by
+    /// one thread is immediately visible to the others through the shared table. The first worker
+    /// to resolve the root signals the rest to stop via a shared `AtomicBool`. The returned eval is
+    /// that first finisher's, while the returned `col` is re-derived from the shared table so it is
+    /// independent of which worker happened to finish first.
+    ///
+    /// `threads <= 1` falls back to a plain single-threaded search.
+    ///
+    /// Also returns the principal variation (see `principal_variation`), re-derived from the
+    /// shared table alongside the move for the same reason.
+    pub fn solve_lazy_smp(board: &Board, threads: usize) -> (u8, i8, Vec<u8>) {
+        if threads <= 1 {
+            let mut explorer = Explorer::new();
+            let (mv, eval) = explorer.solve(board);
+            let pv = explorer.principal_variation(board);
+            return (mv, eval, pv);
+        }
+
+        let transpositiontable = Arc::new(TranspositionTable::new());
+        let stop = Arc::new(AtomicBool::new(false));
+        let winner: std::sync::Mutex<Option<i8>> = std::sync::Mutex::new(None);
+
+        thread::scope(|scope| {
+            for worker_id in 0..threads {
+                let table = Arc::clone(&transpositiontable);
+                let stop = Arc::clone(&stop);
+                let winner = &winner;
+
+                scope.spawn(move || {
+                    let mut worker = Explorer::with_shared_state(table, stop);
+                    let eval = worker.evaluate_board_perturbed(board, worker_id);
+
+                    // first worker to finish signals every other worker to stop.
+                    if winner.lock().unwrap().is_none() {
+                        *winner.lock().unwrap() = Some(eval);
+                    }
+                    worker.stop.store(true, Ordering::Relaxed);
+                });
+            }
+        });
+
+        let eval = winner.into_inner().unwrap().expect("at least one worker must finish");
+
+        if board.is_game_over() {
+            return (EMPTY_MOVE, eval, Vec::new());
+        }
+
+        // re-derive the PV from the shared table rather than trusting whichever worker happened
+        // to finish first, so the reported move is consistent regardless of scheduling.
+        let finisher = Explorer::with_shared_state(transpositiontable, Arc::new(AtomicBool::new(false)));
+        let pv = finisher.principal_variation(board);
+        let mv = *pv.first().unwrap_or(&EMPTY_MOVE);
+        (mv, eval, pv)
+    }
+
+    /// reconstructs the principal variation from `board`'s position by walking the transposition
+    /// table, replaying the stored best move at each step. Bounded at `PV_SIZE` plies, and stops
+    /// early if a position's key repeats, so a corrupted or cyclic chain of stored moves can't
+    /// spin this loop forever. Returned as 0-indexed columns, matching the rest of the engine's
+    /// move convention (see `print_eval` in `main.rs` for 1-indexed display).
+    ///
+    /// If the table runs out before the game is decided (the usual case: the last couple of plies
+    /// are resolved by direct lookahead rather than an exact TT entry, since wins/losses one move
+    /// out aren't stored), falls back to that same one-ply lookahead (immediate win, forced loss,
+    /// or any legal move) to extend the line by one more ply.
+    pub fn principal_variation(&self, board: &Board) -> Vec<u8> {
         let mut pv = Vec::new();
         let mut board_cpy = *board;
+        let mut seen_keys = Vec::new();
 
-        loop {
-            // TODO checks if game over.
-            if let Some(entry) = self.transpositiontable.get_exact_entry(&board_cpy) {
-                let mv = entry.get_mv();
-                if board_cpy.add(mv).is_ok() {
-                    pv.push(mv);
-                    continue;
-                }
+        while pv.len() < PV_SIZE {
+            let key = board_cpy.get_unique_position_key();
+            if seen_keys.contains(&key) {
+                break;
             }
+            seen_keys.push(key);
+
+            let mv = self.transpositiontable
+                .get_exact_entry(&board_cpy)
+                .map(|entry| board_cpy.canonicalize_col(entry.get_mv()));
 
-            break;
+            match mv {
+                Some(mv) if board_cpy.add(mv).is_ok() => pv.push(mv),
+                _ => break,
+            }
         }
 
         // Position probably is winning by next move, or losing by next opponent
@@ -110,7 +495,7 @@ impl Explorer {
             pv.push(col);
         }
         // losing case: TODO - needs to be fixed to give the longest line.
-        let (losing_moves, _) = board_cpy.opp_win_moves(possible);
+        let losing_moves = board_cpy.opp_win_moves(possible);
         if losing_moves != 0 {
             let col = Board::pos_to_col(losing_moves);
             pv.push(col);
@@ -169,14 +554,18 @@ impl Explorer {
             let low_sz = 6;
             let high_sz = 6;
             let (mut min, mut max) = (g_min, g_min + low_sz);
+            let mut iteration: u32 = 0;
 
             loop {
+                iteration += 1;
+
                 // -1 and +1 on the bounds in order for us to be able to obtain an exact move.
                 let asp_min = i8::max(min - 1, g_min - 1);
                 let asp_max = i8::min(max + 1, g_max + 1);
 
-                if reset_t_table { self.transpositiontable.clear(); }
+                if reset_t_table { self.transpositiontable.clear(); self.reset_move_ordering_heuristics(); }
                 let eval = self.search(board, asp_min, asp_max, Board::move_score);
+                self.report_progress(board, iteration, eval);
 
                 if asp_min < eval && eval < asp_max {
                     return eval;
@@ -198,11 +587,21 @@ impl Explorer {
             }
         }
         else {
-            if reset_t_table { self.transpositiontable.clear(); }
-            return self.search(board, start_min - 1, start_max + 1, Board::move_score);
+            if reset_t_table { self.transpositiontable.clear(); self.reset_move_ordering_heuristics(); }
+            let eval = self.search(board, start_min - 1, start_max + 1, Board::move_score);
+            self.report_progress(board, 1, eval);
+            eval
         }
     }
 
+    /// clears the killer-move table and history heuristic. Called alongside a transposition table
+    /// reset, since stale killers/history from a previous, unrelated position are more likely to
+    /// mislead move ordering than to help it.
+    fn reset_move_ordering_heuristics(&mut self) {
+        self.killers = [[EMPTY_MOVE; 2]; PV_SIZE];
+        self.history = [0; WIDTH as usize];
+    }
+
     /// Searches for the most optimal evaluation after loading in a board.
     /// Applies these optimizations:
     /// * alpha-beta pruning
@@ -224,15 +623,42 @@ impl Explorer {
         // increment nodes searched.
         self.nodes_explored += 1;
 
+        // a Lazy SMP sibling may have already resolved the root; stop promptly rather than
+        // burning more of this thread's time on a result nobody will use. Checked periodically,
+        // not every node, so the atomic load doesn't show up in the hot path.
+        if self.nodes_explored % STOP_POLL_INTERVAL == 0 {
+            if self.stop.load(Ordering::Relaxed) {
+                return a;
+            }
+            if matches!(self.deadline, Some(deadline) if Instant::now() >= deadline) {
+                self.stop.store(true, Ordering::Relaxed);
+                return a;
+            }
+        }
+
         if board.is_filled() { // the position is drawn.
             // we do not need to check if move is win, because winning is already checked before
             // the recursive call (via endgame lookahead).
             return TIE_SCORE;
         }
 
+        let moves_played = board.moves_played();
+
+        // a sampled endgame tablebase hit (see `PersistentTable::build_endgame` and
+        // `with_endgame_table`) is an exact score for this exact position, so it can be returned
+        // immediately, the same way `probe_book` short-circuits `solve` at the root.
+        if let Some(eval) = self.probe_endgame_table(board) {
+            return eval;
+        }
+
+        // few enough cells remain that the transposition table and move ordering no longer pay
+        // for themselves; hand off to the stripped solver instead (see `ENDGAME_CELL_THRESHOLD`).
+        if SIZE - moves_played as u8 <= ENDGAME_CELL_THRESHOLD {
+            return self.search_endgame(board, a, b);
+        }
+
         let possible = board.possible_moves();
         let winning_moves = board.player_win_moves(possible);
-        let moves_played = board.moves_played();
 
         // the unique key to represent the board in order to insert or search transposition table.
         let board_key = board.get_unique_position_key();
@@ -269,7 +695,8 @@ impl Explorer {
 
         // look up evaluation in transposition table. Updates the best refutation.
         let mut refutation = EMPTY_MOVE;
-        if let Some(entry) = self.transpositiontable.get_entry_with_key(board_key) {
+        let probed: Option<Entry> = self.transpositiontable.get_entry_with_key(board_key);
+        if let Some(entry) = probed {
             let flag = entry.get_flag();
             let val = entry.get_eval();
 
@@ -278,7 +705,7 @@ impl Explorer {
             }
             else if flag == FLAG_LOWER { // Failed high. We can update refutation move.
                 a = i8::max(a, val);
-                refutation = entry.get_mv();
+                refutation = board.canonicalize_col(entry.get_mv());
             }
             else { // exact node.
                 return val;
@@ -290,13 +717,22 @@ impl Explorer {
         }
 
         // generates ordered moves to search.
+        let ply = moves_played as usize;
         let mut next_moves = ScoredMoves::new();
         for (m, c) in Moves::new(non_losing_moves) {
             if refutation == c { // prioritize searching refutation move first.
                 next_moves.add(m, c, REFUTATION_SCORE);
             }
             else {
-                next_moves.add(m, c, f(board, m));
+                // Lazy SMP workers add a small, deterministic per-column jitter so that sibling
+                // threads searching the same root diverge into different subtrees instead of all
+                // following the identical move order; a lone explorer has `perturbation == 0` and
+                // is unaffected.
+                let is_killer = self.killers[ply][0] == c || self.killers[ply][1] == c;
+                let history_bonus = u32::min(self.history[c as usize] / 64, (REFUTATION_SCORE - 1) as u32) as i8;
+                next_moves.add_with_context(
+                    m, c, f(board, m) + self.perturbation * (c as i8 % 3 - 1),
+                    is_killer, KILLER_BONUS, history_bonus);
             }
         }
 
@@ -326,8 +762,19 @@ impl Explorer {
             if val >= b {
                 // move inserted is refutation move.
                 // can use this inserted move for move ordering.
-                self.transpositiontable.insert_with_key(board_key, val, FLAG_LOWER, depth, c);
+                self.transpositiontable.insert_with_key(board_key, val, FLAG_LOWER, depth, board.canonicalize_col(c));
                 self.fail_high_nodes += 1;
+
+                // record the cutoff for future move ordering at this ply and overall.
+                if self.killers[ply][0] != c {
+                    self.killers[ply][1] = self.killers[ply][0];
+                    self.killers[ply][0] = c;
+                }
+                // deeper cutoffs are rarer and more informative, so weight the bonus by depth^2
+                // rather than incrementing by a flat 1 each time.
+                let history_bonus = (depth as u32) * (depth as u32);
+                self.history[c as usize] = self.history[c as usize].saturating_add(history_bonus);
+
                 return val;
             }
 
@@ -349,16 +796,276 @@ impl Explorer {
             FLAG_UPPER
         };
 
-        self.transpositiontable.insert_with_key(board_key, final_eval, flag, depth, final_mv);
+        self.transpositiontable.insert_with_key(board_key, final_eval, flag, depth, board.canonicalize_col(final_mv));
         final_eval
     }
 
+    /// depth-limited counterpart to `search`, used by `go_depth`'s iterative-deepening ladder:
+    /// identical win/loss/draw short-circuits, killer/history move ordering, and transposition
+    /// table usage, except that hitting `remaining_depth == 0` on a position that isn't terminal
+    /// returns `heuristic_eval`'s estimate instead of recursing further.
+    ///
+    /// Unlike `search`, the transposition table's `depth` field holds `remaining_depth` rather
+    /// than ply, so a probed entry is only trusted as a bound/exact value if it was stored at a
+    /// `remaining_depth` at least as large as what this call needs; a shallower hit still seeds
+    /// move ordering as a refutation move, the same way `search` uses the table.
+    fn search_depth(&mut self, board: &Board, mut a: i8, mut b: i8, remaining_depth: u8) -> i8 {
+        self.nodes_explored += 1;
+
+        if self.nodes_explored % STOP_POLL_INTERVAL == 0 {
+            if self.stop.load(Ordering::Relaxed) {
+                return a;
+            }
+            if matches!(self.deadline, Some(deadline) if Instant::now() >= deadline) {
+                self.stop.store(true, Ordering::Relaxed);
+                return a;
+            }
+        }
+
+        if board.is_filled() {
+            return TIE_SCORE;
+        }
+
+        let moves_played = board.moves_played();
+        let possible = board.possible_moves();
+        let winning_moves = board.player_win_moves(possible);
+        if winning_moves != 0 {
+            return Explorer::win_eval(moves_played + 1);
+        }
+
+        // looks for possible moves that don't lose the game immediately.
+        let opp_threats = board.opp_win_moves(possible);
+        let non_losing_moves = if opp_threats == 0 {
+            possible
+        } else if Board::at_most_one_bit_set(opp_threats) {
+            opp_threats
+        } else {
+            0
+        };
+
+        if non_losing_moves == 0 { // all moves will lose.
+            return -Explorer::win_eval(moves_played + 2);
+        }
+
+        if remaining_depth == 0 {
+            return Explorer::heuristic_eval(board);
+        }
+
+        let board_key = board.get_unique_position_key();
+
+        let mut refutation = EMPTY_MOVE;
+        if let Some(entry) = self.depth_table.get_entry_with_key(board_key) {
+            if entry.get_depth() >= remaining_depth {
+                let flag = entry.get_flag();
+                let val = entry.get_eval();
+
+                if flag == FLAG_UPPER {
+                    b = i8::min(b, val);
+                } else if flag == FLAG_LOWER {
+                    a = i8::max(a, val);
+                    refutation = board.canonicalize_col(entry.get_mv());
+                } else {
+                    return val;
+                }
+
+                if a >= b {
+                    return val;
+                }
+            } else if entry.get_flag() != FLAG_UPPER {
+                // too shallow to trust its bound, but still a reasonable move-ordering hint.
+                refutation = board.canonicalize_col(entry.get_mv());
+            }
+        }
+
+        let ply = moves_played as usize;
+        let mut next_moves = ScoredMoves::new();
+        for (m, c) in Moves::new(non_losing_moves) {
+            if refutation == c {
+                next_moves.add(m, c, REFUTATION_SCORE);
+            } else {
+                let is_killer = self.killers[ply][0] == c || self.killers[ply][1] == c;
+                let history_bonus = u32::min(self.history[c as usize] / 64, (REFUTATION_SCORE - 1) as u32) as i8;
+                next_moves.add_with_context(
+                    m, c, Board::move_score(board, m),
+                    is_killer, KILLER_BONUS, history_bonus);
+            }
+        }
+
+        let mut final_eval = -MAX_SCORE;
+        let mut final_mv = EMPTY_MOVE;
+        let mut boardcpy = *board;
+        let a_orig = a;
+
+        for (i, (m, c)) in next_moves.enumerate() {
+            boardcpy.play(m);
+
+            let mut val;
+            if i == 0 {
+                val = -self.search_depth(&boardcpy, -b, -a, remaining_depth - 1);
+            } else {
+                val = -self.search_depth(&boardcpy, -a - 1, -a, remaining_depth - 1);
+
+                if a < val && val < b {
+                    val = -self.search_depth(&boardcpy, -b, -val, remaining_depth - 1);
+                }
+            }
+
+            if val >= b {
+                self.depth_table.insert_with_key(board_key, val, FLAG_LOWER, remaining_depth, board.canonicalize_col(c));
+                self.fail_high_nodes += 1;
+
+                if self.killers[ply][0] != c {
+                    self.killers[ply][1] = self.killers[ply][0];
+                    self.killers[ply][0] = c;
+                }
+                let history_bonus = (remaining_depth as u32) * (remaining_depth as u32);
+                self.history[c as usize] = self.history[c as usize].saturating_add(history_bonus);
+
+                boardcpy.revert(m);
+                return val;
+            }
+
+            if val > final_eval {
+                a = i8::max(val, a);
+                final_eval = val;
+                final_mv = c;
+            }
+
+            boardcpy.revert(m);
+        }
+
+        let flag = if a > a_orig {
+            FLAG_EXACT
+        } else {
+            self.fail_low_nodes += 1;
+            FLAG_UPPER
+        };
+
+        self.depth_table.insert_with_key(board_key, final_eval, flag, remaining_depth, board.canonicalize_col(final_mv));
+        final_eval
+    }
+
+    /// stripped alpha-beta used for the final `ENDGAME_CELL_THRESHOLD` cells of the game: no
+    /// transposition table probes/inserts and no killer/history move ordering, just immediate-win
+    /// and forced-block short-circuits over the cheap bitboard routines (`possible_moves`,
+    /// `player_win_moves`, `opp_win_moves`). Returns exactly the same evaluation `search` would
+    /// for the same position - just without paying for heuristics that only pay for themselves
+    /// when there's a deep tree left to prune.
+    fn search_endgame(&mut self, board: &Board, mut a: i8, mut b: i8) -> i8 {
+        self.nodes_explored += 1;
+
+        if self.nodes_explored % STOP_POLL_INTERVAL == 0 {
+            if self.stop.load(Ordering::Relaxed) {
+                return a;
+            }
+            if matches!(self.deadline, Some(deadline) if Instant::now() >= deadline) {
+                self.stop.store(true, Ordering::Relaxed);
+                return a;
+            }
+        }
+
+        if board.is_filled() { // the position is drawn.
+            return TIE_SCORE;
+        }
+
+        let possible = board.possible_moves();
+        let moves_played = board.moves_played();
+
+        // quick endgame lookahead. checks if can win in 1 move.
+        let winning_moves = board.player_win_moves(possible);
+        if winning_moves != 0 {
+            return Explorer::win_eval(moves_played + 1);
+        }
+
+        // a move is only worth searching if it doesn't hand the opponent an immediate win next
+        // turn: if they have exactly one winning reply, we're forced to block it; if they have
+        // more than one, nothing we play stops both, so we lose regardless.
+        let opp_threats = board.opp_win_moves(possible);
+        let non_losing_moves = if opp_threats == 0 {
+            possible
+        } else if Board::at_most_one_bit_set(opp_threats) {
+            opp_threats
+        } else {
+            0
+        };
+
+        if non_losing_moves == 0 { // all moves will lose.
+            return -Explorer::win_eval(moves_played + 2);
+        }
+
+        // same window-tightening as `search`: cheap arithmetic, no hashing involved.
+        let min_eval = -Explorer::win_eval(moves_played + 3);
+        a = i8::max(a, min_eval);
+        let max_eval = Explorer::win_eval(moves_played + 2);
+        b = i8::min(b, max_eval);
+
+        if a >= b {
+            return a;
+        }
+
+        let mut best = -MAX_SCORE;
+        let mut boardcpy = *board;
+
+        for (m, _) in Moves::new(non_losing_moves) {
+            boardcpy.play(m);
+            let val = -self.search_endgame(&boardcpy, -b, -a);
+            boardcpy.revert(m);
+
+            if val >= b { // fail-high beta cutoff.
+                return val;
+            }
+            if val > best {
+                best = val;
+                a = i8::max(a, val);
+            }
+        }
+
+        best
+    }
+
     /// Assumes the game finished in `moves_played` number of moves, and assigns a score to the
     /// winner.
     fn win_eval(moves_played: u32) -> i8 {
         MAX_SCORE - moves_played as i8
     }
 
+    /// bit position of `EVALTABLE`'s `i`-th entry: `EVALTABLE` is laid out top-row-first, the same
+    /// orientation `Board`'s `Display` impl prints, so this mirrors that impl's `(row, col)`
+    /// derivation rather than inventing a new one.
+    fn evaltable_bit(i: usize) -> usize {
+        let col = i % WIDTH as usize;
+        let row = HEIGHT as usize - i / WIDTH as usize - 1;
+        row + col * COUNTS_PER_COL as usize
+    }
+
+    /// non-exact positional score for `board`'s current player, used by `search_depth` once
+    /// `remaining_depth` hits 0 on a non-terminal position: sums `EVALTABLE`'s per-cell weight
+    /// (roughly, how many 4-in-a-row lines pass through that cell) over each player's occupied
+    /// squares and takes the difference.
+    ///
+    /// The raw difference is then compressed to `-1`, `0`, or `1` - strictly inside the gap
+    /// between `TIE_SCORE` (0) and the smallest possible true win/loss magnitude (`win_eval(SIZE)
+    /// == 2`) - so a heuristic guess can never be mistaken for, or overwrite, an exact mate score
+    /// read back out of the shared transposition table. This makes the heuristic coarse (only a
+    /// three-way "who's ahead" signal) by construction, since it shares the same `i8` channel the
+    /// exact solver's scores live in.
+    fn heuristic_eval(board: &Board) -> i8 {
+        let player = board.get_curr_player_pos();
+        let opp = board.get_opp_player_pos();
+
+        let mut diff: i32 = 0;
+        for (i, &weight) in EVALTABLE.iter().enumerate() {
+            let bit = 1 << Explorer::evaltable_bit(i);
+            if player & bit != 0 {
+                diff += weight as i32;
+            } else if opp & bit != 0 {
+                diff -= weight as i32;
+            }
+        }
+
+        diff.clamp(-1, 1) as i8
+    }
+
     /// returns the number of nodes explored.
     pub fn get_nodes_explored(&self) -> usize {
         self.nodes_explored
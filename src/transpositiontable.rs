@@ -1,3 +1,10 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
 use crate::{board::Board, moves::EMPTY_MOVE};
 
 /// Using the Chinese remainder theorem, using our key (which could be encoded in 49 bits), the
@@ -26,7 +33,7 @@ const STORED_KEY_BIT_MASK: u64 = (1 << STORED_KEY_BITS) - 1;
 /// empty key.
 const EMPTY_KEY: u32 = u32::MAX;
 
-/// represents an entry of the transposition table.
+/// represents a decoded entry of the transposition table.
 ///
 /// stored_key: lower 32 bit of 49-bit board key.
 /// eval: evaluation of the position.
@@ -82,41 +89,123 @@ impl Default for Entry {
     }
 }
 
+/// number of bits used by the packed, non-key payload of a slot: `mv` (u3, bits 0-2), `depth` (u8,
+/// bits 3-10), `flag` (u2, bits 11-12), `eval` (i8, bits 13-20), and a `valid` marker bit (bit 21).
+/// Each field gets its own non-overlapping range of bits.
+const PAYLOAD_BITS: u32 = 1 + 8 + 2 + 8 + 3;
+const PAYLOAD_MASK: u64 = (1 << PAYLOAD_BITS) - 1;
+const VALID_BIT: u64 = 1 << (PAYLOAD_BITS - 1);
+
+/// A single slot of the lock-free transposition table, packed into one `AtomicU64` so it can be
+/// probed and replaced from multiple search threads without a mutex.
+///
+/// Uses Hyatt's lockless-hashing trick: the low `PAYLOAD_BITS` bits hold the decoded payload in
+/// the clear (valid marker, eval, flag, depth, mv), while the next 32 bits hold `key ^ payload`
+/// instead of the raw key. A reader recomputes `(word >> PAYLOAD_BITS) ^ payload` and only treats
+/// the slot as a hit if that matches the probe key; a torn read across the two halves of the word
+/// (e.g. a concurrent writer overwriting one half mid-read) produces a mismatched XOR, which is
+/// simply treated as a miss rather than returning corrupted data.
+#[derive(Debug)]
+struct AtomicEntry(AtomicU64);
+
+impl AtomicEntry {
+    fn empty() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    fn pack(key: u64, eval: i8, flag: Flag, depth: u8, mv: u8) -> u64 {
+        let payload = VALID_BIT
+            | (mv as u64)
+            | ((depth as u64) << 3)
+            | ((flag as u64) << 11)
+            | ((eval as u8 as u64) << 13);
+        let masked_key = (key & STORED_KEY_BIT_MASK) ^ payload;
+        payload | (masked_key << PAYLOAD_BITS)
+    }
+
+    fn unpack(word: u64) -> Option<(u32, Entry)> {
+        let payload = word & PAYLOAD_MASK;
+        if payload & VALID_BIT == 0 {
+            return None;
+        }
+
+        let stored_key = (((word >> PAYLOAD_BITS) & STORED_KEY_BIT_MASK) ^ payload) as u32;
+        let mv = (payload & 0b111) as u8;
+        let depth = ((payload >> 3) & 0xFF) as u8;
+        let flag = ((payload >> 11) & 0b11) as u8;
+        let eval = ((payload >> 13) & 0xFF) as u8 as i8;
+
+        Some((stored_key, Entry { stored_key, eval, flag, depth, mv }))
+    }
+
+    /// atomically stores the given entry into this slot.
+    fn store(&self, key: u64, eval: i8, flag: Flag, depth: u8, mv: u8) {
+        self.0.store(Self::pack(key, eval, flag, depth, mv), Ordering::Relaxed);
+    }
+
+    /// atomically loads this slot, returning the stored key bits (for comparison against a probe
+    /// key) and the decoded entry, or `None` if the slot is empty or the XOR check failed.
+    fn load(&self) -> Option<(u32, Entry)> {
+        Self::unpack(self.0.load(Ordering::Relaxed))
+    }
+
+    /// the depth recorded in this slot, or `u8::MAX` if the slot is empty (so it always loses the
+    /// depth-preferred replacement comparison).
+    fn depth(&self) -> u8 {
+        self.load().map_or(u8::MAX, |(_, entry)| entry.get_depth())
+    }
+
+    fn clear(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Clone for AtomicEntry {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.0.load(Ordering::Relaxed)))
+    }
+}
+
 #[derive(Debug)]
 pub struct TranspositionTable {
-    /// each entry of the table consists of 2 entries, with 2 different replacement policies:
-    /// table_entry.0 = entry that is always replaced by new entries.
+    /// each entry of the table consists of 2 slots, with 2 different replacement policies:
+    /// table_entry.0 = slot that is always replaced by new entries.
     /// table_entry.1 = replacement only happens when new entry has depth < existing.
     ///
     /// since depth == moves_made, a smaller moves_made means we can scan less of the
     /// tree if we cache that result.
-    table: Vec<(Entry, Entry)>
+    ///
+    /// Each slot is an independent `AtomicEntry`, so probing and inserting never takes a lock:
+    /// this lets multiple Lazy SMP search threads share one table.
+    table: Vec<(AtomicEntry, AtomicEntry)>
 }
 
 impl TranspositionTable {
 
     /// initializes the new Transposition Table
     pub fn new() -> Self {
-        let entries = ( Entry::default(), Entry::default() );
+        let entries = ( AtomicEntry::empty(), AtomicEntry::empty() );
         Self { table: vec![entries; MAX_TABLE_SIZE] }
     }
 
     /// inserts the board game state and evaluation into the transposition table.
-    pub fn insert(&mut self, board: &Board, eval: i8, flag: Flag, depth: u8, mv: u8) {
+    pub fn insert(&self, board: &Board, eval: i8, flag: Flag, depth: u8, mv: u8) {
         let key = board.get_unique_position_key();
         self.insert_with_key(key, eval, flag, depth, mv);
     }
 
-    /// inserts the board game state and eval into transposition table using key.
-    pub fn insert_with_key(&mut self, key: u64, eval: i8, flag: Flag, depth: u8, mv: u8) {
-        let entry = Entry::new(key, eval, flag, depth, mv);
+    /// inserts the board game state and eval into transposition table using key. Lock-free: each
+    /// slot is updated via an independent atomic store.
+    pub fn insert_with_key(&self, key: u64, eval: i8, flag: Flag, depth: u8, mv: u8) {
         let loc = TranspositionTable::location(key);
-        self.table[loc].0 = entry.clone(); // always replace
+        let (always, depth_preferred) = &self.table[loc];
+
+        // always replace.
+        always.store(key, eval, flag, depth, mv);
 
-        // replace 1 entry only if depth is lower.
-        let orig_entry = &self.table[loc].1;
-        if depth < orig_entry.get_depth() {
-            self.table[loc].1 = entry;
+        // replace the depth-preferred slot only if the new entry has a lower (shallower) depth.
+        if depth < depth_preferred.depth() {
+            depth_preferred.store(key, eval, flag, depth, mv);
         }
     }
 
@@ -127,46 +216,122 @@ impl TranspositionTable {
     }
 
     /// Gets the entry using the given board to calculate the key.
-    /// bool determines whether the key matches (whether entry is valid).
-    pub fn get_entry(&self, board: &Board) -> Option<&Entry> {
+    pub fn get_entry(&self, board: &Board) -> Option<Entry> {
         let key = board.get_unique_position_key();
         self.get_entry_with_key(key)
     }
 
-    pub fn get_exact_entry(&self, board: &Board) -> Option<&Entry> {
+    pub fn get_exact_entry(&self, board: &Board) -> Option<Entry> {
         let key = board.get_unique_position_key();
         let new_key = (key & STORED_KEY_BIT_MASK) as u32;
         let loc = TranspositionTable::location(key);
-        let entry = &self.table[loc];
+        let (always, depth_preferred) = &self.table[loc];
 
-        if entry.0.get_key() == new_key && entry.0.get_flag() == FLAG_EXACT {
-            return Some(&entry.0);
-        } else if entry.1.get_key() == new_key && entry.1.get_flag() == FLAG_EXACT {
-            return Some(&entry.1);
-        } else {
-            return None;
+        if let Some((k, entry)) = always.load() {
+            if k == new_key && entry.get_flag() == FLAG_EXACT {
+                return Some(entry);
+            }
+        }
+        if let Some((k, entry)) = depth_preferred.load() {
+            if k == new_key && entry.get_flag() == FLAG_EXACT {
+                return Some(entry);
+            }
         }
+
+        None
     }
 
-    /// obtains the selected entry, given a key.
-    pub fn get_entry_with_key(&self, key: u64) -> Option<&Entry> {
+    /// obtains the selected entry, given a key. Lock-free: each slot is probed via an independent
+    /// atomic load, and the XOR check rejects any torn read as a miss.
+    pub fn get_entry_with_key(&self, key: u64) -> Option<Entry> {
         let loc = TranspositionTable::location(key);
-        let (entry0, entry1) = &self.table[loc];
+        let (always, depth_preferred) = &self.table[loc];
         let new_key = (key & STORED_KEY_BIT_MASK) as u32;
 
-        if entry0.get_key() == new_key {
-            return Some(entry0);
-        } else if entry1.get_key() == new_key {
-            return Some(entry1);
-        } else {
-            return None
+        if let Some((k, entry)) = always.load() {
+            if k == new_key {
+                return Some(entry);
+            }
+        }
+        if let Some((k, entry)) = depth_preferred.load() {
+            if k == new_key {
+                return Some(entry);
+            }
+        }
+
+        None
+    }
+
+    pub fn clear(&self) {
+        for (always, depth_preferred) in self.table.iter() {
+            always.clear();
+            depth_preferred.clear();
         }
     }
 
-    pub fn clear(&mut self) {
-        for entry in self.table.iter_mut() {
-            entry.0.clear();
-            entry.1.clear();
+    /// saves every occupied slot to `path` as a compact binary dump, so a long solve can be
+    /// checkpointed and resumed, or shipped as a pre-warmed cache to search against later (see
+    /// `load`, and the `--tt-save`/`--tt-load` CLI flags).
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut records = Vec::new();
+
+        for (loc, (always, depth_preferred)) in self.table.iter().enumerate() {
+            if let Some((stored_key, entry)) = always.load() {
+                records.push(SavedRecord::new(loc as u32, 0, stored_key, &entry));
+            }
+            if let Some((stored_key, entry)) = depth_preferred.load() {
+                records.push(SavedRecord::new(loc as u32, 1, stored_key, &entry));
+            }
+        }
+
+        let file = File::create(path)?;
+        bincode::serialize_into(file, &records)?;
+        Ok(())
+    }
+
+    /// loads a table previously written by `save`, re-inserting each record into the exact slot
+    /// it was saved from.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let records: Vec<SavedRecord> = bincode::deserialize_from(file)?;
+
+        let table = Self::new();
+        for record in records {
+            let (always, depth_preferred) = &table.table[record.loc as usize];
+            let slot = if record.slot == 0 { always } else { depth_preferred };
+            slot.store(record.stored_key as u64, record.eval, record.flag, record.depth, record.mv);
+        }
+
+        Ok(table)
+    }
+}
+
+/// one occupied slot as written to/read from a `save` file: the stored (lower 32 bits of the)
+/// key, the slot's replacement-policy role within its pair (0 = always-replace, 1 =
+/// depth-preferred), and the decoded payload. Kept separate from `AtomicEntry`'s packed in-memory
+/// layout so the on-disk format doesn't depend on the XOR-hashing trick used to make that layout
+/// lock-free.
+#[derive(Serialize, Deserialize)]
+struct SavedRecord {
+    loc: u32,
+    slot: u8,
+    stored_key: u32,
+    eval: i8,
+    flag: u8,
+    depth: u8,
+    mv: u8,
+}
+
+impl SavedRecord {
+    fn new(loc: u32, slot: u8, stored_key: u32, entry: &Entry) -> Self {
+        Self {
+            loc,
+            slot,
+            stored_key,
+            eval: entry.get_eval(),
+            flag: entry.get_flag(),
+            depth: entry.get_depth(),
+            mv: entry.get_mv(),
         }
     }
 }
@@ -0,0 +1,113 @@
+// Connect4EngineRust, a strong solver for the connect-4 board game.
+// Copyright (C) 2023 Derick Tseng
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::board::Board;
+use crate::strategy::{Explorer, SearchInfo};
+
+/// a command sent to a running `AnalysisWorker`'s background thread.
+enum Command {
+    /// solves `Board` from scratch, replying with `(move, eval)` on the first channel once the
+    /// search finishes or is interrupted, and streaming a `SearchInfo` per iteration on the
+    /// second for the duration of that search.
+    Start(Board, Sender<(u8, i8)>, Sender<SearchInfo>),
+
+    /// sets the time budget applied to the next `Start`.
+    SetTimeLimit(Duration),
+
+    /// no-op placeholder processed by the worker loop between searches; stopping an in-progress
+    /// search happens immediately through the shared `stop` flag instead (see `stop()`), since
+    /// the worker thread can't drain the channel again until its current search returns.
+    Stop,
+}
+
+/// runs `Explorer` searches on a dedicated background thread, driven by a stream of `Command`s
+/// sent over an `mpsc` channel, so that callers (e.g. `main.rs`'s stdin loop or the `--movetime`
+/// flag) can cap thinking time or cancel a search without blocking on `Explorer::solve` directly.
+pub struct AnalysisWorker {
+    sender: Sender<Command>,
+    stop: Arc<AtomicBool>,
+}
+
+impl AnalysisWorker {
+    /// spawns the background thread and returns a handle to communicate with it. The worker
+    /// keeps running, and can be reused for any number of `start` calls, until this handle (and
+    /// therefore its `Sender`) is dropped.
+    pub fn spawn() -> Self {
+        let (sender, receiver): (Sender<Command>, Receiver<Command>) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker_stop = Arc::clone(&stop);
+
+        thread::spawn(move || {
+            let mut explorer = Explorer::with_shared_stop(worker_stop);
+            let mut time_limit = None;
+
+            for command in receiver {
+                match command {
+                    Command::Start(board, reply, progress) => {
+                        explorer.set_progress(Some(progress));
+                        let result = explorer.solve_timed(&board, time_limit);
+                        // drop the progress sender now rather than on the next `Start`, so a
+                        // caller streaming `SearchInfo`s sees its channel close as soon as this
+                        // search is actually done.
+                        explorer.set_progress(None);
+                        let _ = reply.send(result);
+                    }
+                    Command::SetTimeLimit(limit) => time_limit = Some(limit),
+                    Command::Stop => {}
+                }
+            }
+        });
+
+        Self { sender, stop }
+    }
+
+    /// queues `board` for analysis; if a search is already in flight, this one starts once that
+    /// search has replied on its own `Receiver`. Returns immediately with a `Receiver` that yields
+    /// `(move, eval)` once this search finishes or is stopped.
+    pub fn start(&self, board: Board) -> Receiver<(u8, i8)> {
+        self.start_with_progress(board).0
+    }
+
+    /// like `start`, but also returns a `Receiver` that yields a `SearchInfo` (iteration, eval, nodes,
+    /// PV) after each iteration of the search's aspiration-window ladder, and closes once the
+    /// search finishes — so a caller can show the search converging instead of only seeing the
+    /// final answer.
+    pub fn start_with_progress(&self, board: Board) -> (Receiver<(u8, i8)>, Receiver<SearchInfo>) {
+        self.stop.store(false, Ordering::Relaxed);
+        let (reply_tx, reply_rx) = mpsc::channel();
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let _ = self.sender.send(Command::Start(board, reply_tx, progress_tx));
+        (reply_rx, progress_rx)
+    }
+
+    /// sets the time budget applied to the next `start`.
+    pub fn set_time_limit(&self, limit: Duration) {
+        let _ = self.sender.send(Command::SetTimeLimit(limit));
+    }
+
+    /// requests that the in-progress search stop as soon as it next polls the shared flag,
+    /// returning the best move found so far instead of the (possibly unreachable) full solve.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.sender.send(Command::Stop);
+    }
+}
@@ -14,8 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use connect4engine::moves::{DEFAULT_ORDER, Moves};
-use connect4engine::board::{HEIGHT, Board, WIDTH, BOTTOM_ROW_MASK, COLUMN_MASK, COUNTS_PER_COL};
+use connect4engine::moves::{DEFAULT_ORDER, EMPTY_MOVE, Moves};
+use connect4engine::board::{
+    HEIGHT, Board, WIDTH, BOTTOM_ROW_MASK, COLUMN_MASK, COUNTS_PER_COL, DIRECTION,
+    COLUMN_MASKS, DIRECTION_SHIFTS,
+};
 
 fn test_winning_line(line: &str) {
     let mut b = Board::new();
@@ -208,6 +211,49 @@ fn test_not_filled() {
     assert!(!b.is_filled());
 }
 
+#[test]
+fn test_column_masks_table() {
+    // the build.rs-generated `COLUMN_MASKS` table must match the runtime formula it replaces in
+    // `col_to_pos`/`col_is_occupied`/`get_height`.
+    for col in 0..WIDTH {
+        let runtime_mask = COLUMN_MASK << (col * COUNTS_PER_COL);
+        assert_eq!(COLUMN_MASKS[col as usize], runtime_mask);
+    }
+}
+
+#[test]
+fn test_direction_shifts_table() {
+    // the build.rs-generated `DIRECTION_SHIFTS` table must match the `dir`/`2 * dir`/`3 * dir`
+    // multiples `winning_moves`/`is_win` used to compute inline.
+    for (i, &dir) in DIRECTION.iter().enumerate() {
+        assert_eq!(DIRECTION_SHIFTS[i], [dir, 2 * dir, 3 * dir]);
+    }
+}
+
+#[test]
+fn test_play_revert_restores_moves_and_threats() {
+    let mut b = Board::new_position("16357157437461355316457465722").unwrap();
+    let possible = b.possible_moves();
+    let orig_moves = b.moves_played();
+    let orig_threats = b.current_threats();
+
+    for (mv, _c) in Moves::new(possible) {
+        b.play(mv);
+        assert_eq!(b.moves_played(), orig_moves + 1);
+        b.revert(mv);
+        assert_eq!(b.moves_played(), orig_moves);
+        assert_eq!(b.current_threats(), orig_threats);
+    }
+}
+
+#[test]
+fn test_current_threats_matches_player_win_moves() {
+    let line = "323232";
+    let b = Board::new_position(line).unwrap();
+    let possible = b.possible_moves();
+    assert_eq!(b.current_threats(), b.player_win_moves(possible));
+}
+
 #[test]
 fn test_unique_position_key() {
     let mut b = Board::new();
@@ -228,3 +274,27 @@ fn test_unique_position_key() {
     let unique_position_key = b.get_unique_position_key();
     assert!(!seen_keys.contains(&unique_position_key));
 }
+
+#[test]
+fn test_canonicalize_col_round_trips_through_a_mirrored_pair() {
+    // col 2 and its horizontal mirror, col 4, produce boards that share a unique position key
+    // but disagree on which one is the orientation `get_unique_position_key` actually
+    // canonicalized to.
+    let mut played_col_2 = Board::new();
+    played_col_2.add(2).unwrap();
+
+    let mut played_col_4 = Board::new();
+    played_col_4.add(4).unwrap();
+
+    assert_eq!(played_col_2.get_unique_position_key(), played_col_4.get_unique_position_key());
+
+    // canonicalizing a move relative to one board, then de-canonicalizing it relative to its
+    // mirror, must land on the mirrored column - this is exactly what storing a move under one
+    // board's orientation and reading it back from the other relies on.
+    for mv in 0..WIDTH {
+        let canonical = played_col_2.canonicalize_col(mv);
+        assert_eq!(played_col_4.canonicalize_col(canonical), WIDTH - 1 - mv);
+    }
+
+    assert_eq!(played_col_2.canonicalize_col(EMPTY_MOVE), EMPTY_MOVE);
+}
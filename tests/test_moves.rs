@@ -14,7 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use connect4engine::scoredmoves::ScoredMoves;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use connect4engine::scoredmoves::{Difficulty, ScoredMoves};
 use connect4engine::moves::{Moves, DEFAULT_ORDER};
 use connect4engine::board::{Board, BOTTOM_ROW_MASK};
 
@@ -81,3 +84,114 @@ fn test_scored_moves_ordering(ordering: &[u8], scores: &[i8], expected_order: &[
 
     assert_eq!(count, scores.len());
 }
+
+/// builds a `ScoredMoves` with one move per `(col, score)` pair in `entries`, adding them in the
+/// order `entries` lists them.
+fn scored_moves_from(entries: &[(u8, i8)]) -> ScoredMoves<i8> {
+    let mut scored_moves = ScoredMoves::new();
+    for &(col, score) in entries {
+        let pos = Board::col_to_pos(BOTTOM_ROW_MASK, col);
+        scored_moves.add(pos, col, score);
+    }
+    scored_moves
+}
+
+#[test]
+fn test_pick_empty_returns_none() {
+    let scored_moves: ScoredMoves<i8> = ScoredMoves::new();
+    let mut rng = StdRng::seed_from_u64(0);
+    assert!(scored_moves.pick(Difficulty::Easy, &mut rng).is_none());
+}
+
+#[test]
+fn test_pick_hard_always_returns_the_single_best_move() {
+    let scored_moves = scored_moves_from(&[(3, 5), (2, 9), (4, 2), (1, 7)]);
+
+    for seed in 0..20 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (_, col) = scored_moves.pick(Difficulty::Hard, &mut rng).unwrap();
+        assert_eq!(col, 2); // the score-9 move.
+    }
+}
+
+#[test]
+fn test_pick_normal_stays_within_top_2_window() {
+    let scored_moves = scored_moves_from(&[(3, 4), (2, 3), (4, 2), (1, 1)]);
+
+    for seed in 0..20 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (_, col) = scored_moves.pick(Difficulty::Normal, &mut rng).unwrap();
+        assert!(col == 3 || col == 2, "Normal picked outside its top-2 window: {}", col);
+    }
+}
+
+#[test]
+fn test_pick_easy_stays_within_top_3_window() {
+    let scored_moves = scored_moves_from(&[(3, 4), (2, 3), (4, 2), (1, 1)]);
+
+    for seed in 0..20 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (_, col) = scored_moves.pick(Difficulty::Easy, &mut rng).unwrap();
+        assert!(col == 3 || col == 2 || col == 4, "Easy picked outside its top-3 window: {}", col);
+    }
+}
+
+#[test]
+fn test_pick_weighted_skips_non_positive_weight_entries() {
+    // window is [(col 3, score 5), (col 2, score 0)]; the second entry's weight clamps to 0, so
+    // every roll in [0, 5) should land on the first entry regardless of seed.
+    let scored_moves = scored_moves_from(&[(3, 5), (2, 0)]);
+
+    for seed in 0..20 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (_, col) = scored_moves.pick_weighted(Difficulty::Normal, &mut rng).unwrap();
+        assert_eq!(col, 3);
+    }
+}
+
+#[test]
+fn test_pick_weighted_falls_back_to_pick_when_all_weights_non_positive() {
+    let scored_moves = scored_moves_from(&[(3, -1), (2, -2), (4, -3)]);
+
+    for seed in 0..20 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (_, col) = scored_moves.pick_weighted(Difficulty::Easy, &mut rng).unwrap();
+        assert!(col == 3 || col == 2 || col == 4);
+    }
+}
+
+#[test]
+fn test_add_with_context_killer_bonus_outranks_plain_score() {
+    // col 1's plain score is lower than col 0's, but col 1 is a killer move, so its boosted score
+    // (score + killer_bonus) should overtake col 0's plain score.
+    let mut scored_moves = ScoredMoves::new();
+    let pos0 = Board::col_to_pos(BOTTOM_ROW_MASK, 0);
+    let pos1 = Board::col_to_pos(BOTTOM_ROW_MASK, 1);
+
+    scored_moves.add_with_context(pos0, 0, 5, false, 10, 0);
+    scored_moves.add_with_context(pos1, 1, 1, true, 10, 0);
+
+    let (_, first_col) = scored_moves.next().unwrap();
+    assert_eq!(first_col, 1);
+
+    let (_, second_col) = scored_moves.next().unwrap();
+    assert_eq!(second_col, 0);
+}
+
+#[test]
+fn test_add_with_context_uses_history_score_when_not_killer() {
+    // neither move is a killer, so each is boosted by its own history_score instead.
+    let mut scored_moves = ScoredMoves::new();
+    let pos0 = Board::col_to_pos(BOTTOM_ROW_MASK, 0);
+    let pos1 = Board::col_to_pos(BOTTOM_ROW_MASK, 1);
+
+    scored_moves.add_with_context(pos0, 0, 1, false, 10, 3); // boosted: 1 + 3 = 4
+    scored_moves.add_with_context(pos1, 1, 2, false, 10, 1); // boosted: 2 + 1 = 3
+
+    let (_, first_col) = scored_moves.next().unwrap();
+    assert_eq!(first_col, 0);
+
+    let (_, second_col) = scored_moves.next().unwrap();
+    assert_eq!(second_col, 1);
+}
+
@@ -0,0 +1,66 @@
+use std::io::Cursor;
+
+use connect4engine::protocol;
+
+/// feeds `commands` (one per line) into `protocol::run` and returns its stdout, split into lines.
+fn run_protocol(commands: &[&str]) -> Vec<String> {
+    let input = Cursor::new(commands.join("\n"));
+    let mut output = Vec::new();
+
+    protocol::run(input, &mut output).unwrap();
+
+    String::from_utf8(output).unwrap().lines().map(String::from).collect()
+}
+
+#[test]
+fn test_isready() {
+    let lines = run_protocol(&["isready"]);
+    assert_eq!(lines, vec!["readyok"]);
+}
+
+#[test]
+fn test_move_then_eval() {
+    // plays one column (1-indexed), then asks for an eval without a move.
+    let lines = run_protocol(&["move 1", "eval"]);
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].starts_with("eval "));
+}
+
+#[test]
+fn test_move_rejects_illegal_column() {
+    let lines = run_protocol(&["move 9"]);
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].starts_with("error"));
+}
+
+#[test]
+fn test_go_depth_reports_bestmove() {
+    let lines = run_protocol(&["go depth 2"]);
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].starts_with("bestmove "));
+}
+
+#[test]
+fn test_go_depth_finds_immediate_win() {
+    // alternating columns 1 and 4 three times each sets up an immediate vertical win for the
+    // player to move in column 1 (mirrors `test_one_move_win` in `tests/test_strategy.rs`).
+    let lines = run_protocol(&["move 1 4 1 4 1 4", "go depth 1"]);
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0], "bestmove 1 (eval 37)");
+}
+
+#[test]
+fn test_newgame_resets_position() {
+    let lines = run_protocol(&["move 1 4 1 4 1 4", "newgame", "move 1 1 1 1", "move 9", "eval"]);
+    // column 1 held three stones before `newgame`; four more only fit (with no error reply) if
+    // the board was reset. The illegal column 9 still fails, and `eval` still replies.
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("error"));
+    assert!(lines[1].starts_with("eval "));
+}
+
+#[test]
+fn test_unknown_command() {
+    let lines = run_protocol(&["frobnicate"]);
+    assert_eq!(lines, vec!["error unknown command frobnicate"]);
+}
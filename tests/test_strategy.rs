@@ -1,4 +1,4 @@
-use connect4engine::{board::Board, strategy::Explorer};
+use connect4engine::{board::{Board, WIDTH}, strategy::Explorer};
 
 #[test]
 fn test_endgame_1() {
@@ -74,6 +74,45 @@ fn test_one_move_win() {
     assert_eq!(turncount, 1);
 }
 
+#[test]
+fn test_solve_multipv() {
+    let line = "141414";
+    let board = Board::new_position(line).unwrap();
+    let mut explorer = Explorer::new();
+
+    let (best_col, best_eval) = explorer.solve(&board);
+    let ranked = explorer.solve_multipv(&board, 3);
+
+    // ranked best-to-worst, and agrees with `solve` on the top move.
+    assert_eq!(ranked[0], (best_col, best_eval));
+    assert!(ranked.windows(2).all(|w| w[0].1 >= w[1].1));
+
+    // at most `n` entries, and never more than the number of legal moves.
+    assert!(ranked.len() <= 3);
+}
+
+#[test]
+fn test_go_depth_finds_immediate_win() {
+    // an immediate win is detected via the same short-circuit `solve` uses, regardless of
+    // `max_depth`, so a depth-limited search should agree with the exact solver here.
+    let line = "141414";
+    let board = Board::new_position(line).unwrap();
+
+    let (best_col, best_eval) = Explorer::new().solve(&board);
+    let (col, eval) = Explorer::new().go_depth(&board, 1);
+
+    assert_eq!(col, best_col);
+    assert_eq!(eval, best_eval);
+}
+
+#[test]
+fn test_go_depth_returns_legal_move() {
+    let board = Board::new();
+    let mut explorer = Explorer::new();
+    let (col, _eval) = explorer.go_depth(&board, 3);
+    assert!(col < WIDTH);
+}
+
 /// runs the game, returning (num_turns, resulting board)
 fn run_game(line: &str) -> (usize, Board) {
     let mut board = Board::new_position(line).unwrap();
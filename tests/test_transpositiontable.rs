@@ -58,6 +58,22 @@ fn test_insert_get_negative() {
     assert_eq!(entry.get_flag(), FLAG_UPPER);
 }
 
+#[test]
+fn test_insert_get_no_mv_eval_overlap() {
+    // mv=0, eval=1 must round-trip unchanged: the packed bit layout gives each field its own
+    // non-overlapping range, so eval's bits can't leak into mv's.
+    let board = Board::new_position("44444752222436656566263375515127171771313").unwrap();
+    let eval = 1;
+    let mv = 0;
+
+    let mut table = TranspositionTable::new();
+    table.insert(&board, eval, FLAG_UPPER, 15, mv);
+    let entry = table.get_entry(&board).unwrap();
+
+    assert_eq!(entry.get_eval(), eval);
+    assert_eq!(entry.get_mv(), mv);
+}
+
 #[test]
 fn test_collision() {
     let mut table = TranspositionTable::new();